@@ -0,0 +1,92 @@
+use alloc::{vec, vec::Vec};
+
+/// How each address has been used since coverage was enabled
+///
+/// Unlike the access log, this is never cleared, so after a playtest it shows the code and data the program never
+/// reached.
+pub struct Coverage {
+    /// First bytes of executed instructions
+    executed: Vec<bool>,
+
+    /// Bytes fetched as instructions or their operands
+    fetched: Vec<bool>,
+    read: Vec<bool>,
+    written: Vec<bool>,
+}
+
+impl Coverage {
+    pub(super) fn new(size: usize) -> Self {
+        Self { executed: vec![false; size], fetched: vec![false; size], read: vec![false; size], written: vec![false; size] }
+    }
+
+    pub(super) fn record_fetch(&mut self, addr: usize, next: usize, is_opcode: bool) {
+        self.executed[addr] |= is_opcode;
+        self.fetched[addr] = true;
+        self.fetched[next] = true;
+    }
+
+    pub(super) fn record_read(&mut self, addr: usize) {
+        self.read[addr] = true;
+    }
+
+    pub(super) fn record_write(&mut self, addr: usize) {
+        self.written[addr] = true;
+    }
+
+    pub fn len(&self) -> usize {
+        self.executed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.executed.is_empty()
+    }
+
+    /// Returns true if an instruction starting at `addr` was executed
+    pub fn was_executed(&self, addr: usize) -> bool {
+        self.executed[addr]
+    }
+
+    /// Returns true if the byte was fetched as part of an instruction
+    pub fn was_fetched(&self, addr: usize) -> bool {
+        self.fetched[addr]
+    }
+
+    /// Returns true if the byte was read as data (sprites, Fx65, BCD and the like)
+    pub fn was_read(&self, addr: usize) -> bool {
+        self.read[addr]
+    }
+
+    pub fn was_written(&self, addr: usize) -> bool {
+        self.written[addr]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Core;
+
+    #[test]
+    fn draw_store_and_load() {
+        // Draws the sprite at 210, stores V0 and V1 at 300 and loads them back, then halts at 20C
+        let rom = [
+            0xA2, 0x10, 0xD0, 0x11, 0xA3, 0x00, 0xF1, 0x55, 0xA3, 0x00, 0xF1, 0x65, 0x12, 0x0C, 0x00, 0x00,
+            0xFF,
+        ];
+
+        let mut core = Core::new(&rom, 0);
+
+        core.bus_mut().enable_coverage();
+        core.frame();
+        core.frame();
+
+        let coverage = core.bus().coverage().unwrap();
+
+        assert!((0x200..0x20E).step_by(2).all(|addr| coverage.was_executed(addr) && coverage.was_fetched(addr + 1)));
+        assert!(!coverage.was_executed(0x201) && !coverage.was_executed(0x20E) && !coverage.was_fetched(0x20E));
+
+        assert!(coverage.was_read(0x210) && !coverage.was_read(0x211) && !coverage.was_fetched(0x210));
+        assert!(coverage.was_read(0x300) && coverage.was_read(0x301) && !coverage.was_read(0x302));
+        assert!(coverage.was_written(0x300) && coverage.was_written(0x301) && !coverage.was_written(0x302));
+        assert!(!coverage.was_written(0x210) && !coverage.was_read(0x200));
+    }
+}
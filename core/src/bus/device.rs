@@ -0,0 +1,51 @@
+use alloc::vec::Vec;
+
+/// Peripheral mapped into a range of the address space
+///
+/// Offsets are relative to the start of the range the device is mapped at.
+pub trait BusDevice {
+    fn read(&mut self, offset: u32) -> u8;
+
+    /// Returns false if the device rejects the write, which raises a bus fault
+    fn write(&mut self, offset: u32, data: u8) -> bool;
+
+    /// Read without side effects, for instruction fetches, debuggers and viewers
+    fn peek(&self, offset: u32) -> u8;
+}
+
+/// What happens to writes into read-only memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnWrite {
+    /// Silently dropped, as on hardware without a write line
+    Ignore,
+
+    /// Dropped and reported as a bus fault
+    Fault,
+}
+
+/// Read-only memory holding its own data (program or font ROM)
+pub struct Rom {
+    data: Vec<u8>,
+    on_write: OnWrite,
+}
+
+impl Rom {
+    pub fn new(data: Vec<u8>, on_write: OnWrite) -> Self {
+        Self { data, on_write }
+    }
+}
+
+impl BusDevice for Rom {
+    fn read(&mut self, offset: u32) -> u8 {
+        self.peek(offset)
+    }
+
+    fn write(&mut self, _offset: u32, _data: u8) -> bool {
+        self.on_write == OnWrite::Ignore
+    }
+
+    /// Reads past the end of the data return 0xFF, like an unconnected bus
+    fn peek(&self, offset: u32) -> u8 {
+        self.data.get(offset as usize).copied().unwrap_or(0xFF)
+    }
+}
@@ -1,15 +1,32 @@
-use std::ops::{Index, IndexMut, Range};
+use alloc::{vec, vec::Vec};
+use core::ops::{Index, IndexMut, Range};
 
 /// Chip-8 RAM
-pub struct Memory([u8; Self::SIZE]);
+pub struct Memory(Vec<u8>);
 
 impl Memory {
+    /// Standard Chip-8 memory size
     pub const SIZE: usize = 0x1000;
+
+    /// Creates zeroed memory of `size` bytes, which must be a power of two so that addresses can wrap around
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two());
+
+        Self(vec![0; size])
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl Default for Memory {
     fn default() -> Self {
-        Self([0; Self::SIZE])
+        Self::new(Self::SIZE)
     }
 }
 
@@ -0,0 +1,454 @@
+use crate::bus::{coverage::Coverage, device::{BusDevice, OnWrite}, memory::Memory};
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{fmt, ops::Range};
+
+pub mod coverage;
+pub mod device;
+pub mod memory;
+
+/// Chip-8 address (12-bit, or 24-bit on MegaChip), wrapped around the memory size on access
+#[derive(Clone, Copy)]
+pub struct Address(u32);
+
+impl Address {
+    pub fn new<A: Into<u32>>(addr: A) -> Self {
+        Self(addr.into())
+    }
+
+    /// Masked address for byte accesses
+    pub fn masked_address(&self, mem: &Memory) -> usize {
+        self.0 as usize & (mem.len() - 1)
+    }
+
+    /// Masked next address for word accesses
+    pub fn masked_next_address(&self, mem: &Memory) -> usize {
+        self.0.wrapping_add(1) as usize & (mem.len() - 1)
+    }
+}
+
+/// Addresses read and written since the log was last cleared
+pub struct AccessLog {
+    read: Vec<bool>,
+    written: Vec<bool>,
+}
+
+impl AccessLog {
+    pub fn was_read(&self, addr: usize) -> bool {
+        self.read[addr]
+    }
+
+    pub fn was_written(&self, addr: usize) -> bool {
+        self.written[addr]
+    }
+
+    pub fn clear(&mut self) {
+        self.read.fill(false);
+        self.written.fill(false);
+    }
+}
+
+impl AccessLog {
+    fn new(size: usize) -> Self {
+        Self { read: vec![false; size], written: vec![false; size] }
+    }
+}
+
+/// What a mapped range of addresses is backed by
+enum Region {
+    Device(Box<dyn BusDevice>),
+
+    /// RAM that programs cannot write
+    ReadOnly(OnWrite),
+}
+
+struct Mapping {
+    range: Range<u32>,
+    region: Region,
+}
+
+/// Memory access, with the data written
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write(u8),
+}
+
+/// Why an access was reported
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaultKind {
+    /// A device or read-only region rejected the write
+    Rejected,
+
+    /// Strict mode: the address is past the end of memory and wrapped around (usually I grown too far)
+    OutOfRange,
+
+    /// Strict mode: read of a byte that neither the loader nor the program wrote
+    Uninitialized,
+
+    /// Strict mode: write into the font and interpreter area
+    Reserved,
+
+    /// A 0nnn machine code routine idled or ran too long without returning; the address is where it stopped
+    NoReturn,
+}
+
+/// Access rejected by the bus or flagged by strict mode, or a machine code routine that never returned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BusFault {
+    pub kind: FaultKind,
+
+    /// Address as given, before wrapping
+    pub addr: u32,
+    pub access: Access,
+}
+
+impl fmt::Display for BusFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.kind == FaultKind::NoReturn {
+            return write!(f, "machine code routine stopped at {:03X} without returning", self.addr);
+        }
+
+        match self.access {
+            Access::Read => write!(f, "read of {:03X}", self.addr)?,
+            Access::Write(data) => write!(f, "write of {:02X} to {:03X}", data, self.addr)?,
+        }
+
+        match self.kind {
+            FaultKind::Rejected => write!(f, " was rejected"),
+            FaultKind::OutOfRange => write!(f, " is past the end of memory"),
+            FaultKind::Uninitialized => write!(f, ", which was never written"),
+            FaultKind::Reserved => write!(f, " into the interpreter area"),
+            FaultKind::NoReturn => Ok(()),
+        }
+    }
+}
+
+/// What strict mode does about a suspicious access (the access itself still happens)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnViolation {
+    /// Collected for `take_violations`
+    Log,
+
+    /// Raised as a bus fault, which ends the frame
+    Fault,
+}
+
+/// Access flagged by strict mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Violation {
+    /// Address of the instruction that made the access
+    pub at: u32,
+    pub fault: BusFault,
+}
+
+/// Strict mode settings and the bytes written so far
+struct Strict {
+    on_violation: OnViolation,
+
+    /// Addresses programs should not write (font and interpreter area)
+    reserved: Range<u32>,
+    initialized: Vec<bool>,
+}
+
+/// Memory plus the devices and read-only regions mapped over it
+///
+/// Accesses outside of every mapping go to RAM. Mappings added later take precedence over earlier ones.
+pub struct Bus {
+    mem: Memory,
+    log: AccessLog,
+    mappings: Vec<Mapping>,
+
+    /// Cumulative record of fetches, reads and writes, once enabled
+    coverage: Option<Coverage>,
+    strict: Option<Strict>,
+
+    /// Address of the last instruction fetched, for strict mode reports
+    fetched_at: u32,
+    violations: Vec<Violation>,
+
+    /// First fault since the last `take_fault`
+    fault: Option<BusFault>,
+}
+
+impl Bus {
+    pub fn new(mem: Memory) -> Self {
+        Self {
+            log: AccessLog::new(mem.len()),
+            mem,
+            mappings: Vec::new(),
+            coverage: None,
+            strict: None,
+            fetched_at: 0,
+            violations: Vec::new(),
+            fault: None,
+        }
+    }
+
+    /// Attaches a device to a range of addresses
+    pub fn map(&mut self, range: Range<u32>, device: Box<dyn BusDevice>) {
+        self.mappings.push(Mapping { range, region: Region::Device(device) });
+    }
+
+    /// Makes a range of RAM read-only
+    pub fn protect(&mut self, range: Range<u32>, on_write: OnWrite) {
+        self.mappings.push(Mapping { range, region: Region::ReadOnly(on_write) });
+    }
+
+    fn mapping(&self, addr: usize) -> Option<&Mapping> {
+        self.mappings.iter().rev().find(|mapping| mapping.range.contains(&(addr as u32)))
+    }
+
+    fn mapping_mut(&mut self, addr: usize) -> Option<&mut Mapping> {
+        self.mappings.iter_mut().rev().find(|mapping| mapping.range.contains(&(addr as u32)))
+    }
+
+    /// Byte at a masked address, without side effects
+    fn peek_masked(&self, addr: usize) -> u8 {
+        match self.mapping(addr) {
+            Some(Mapping { range, region: Region::Device(device) }) => device.peek(addr as u32 - range.start),
+            _ => self.mem[addr],
+        }
+    }
+
+    /// Makes the bus report accesses past the end of memory, reads of bytes never written and writes into `reserved`
+    ///
+    /// Only the bytes written from here on (or marked with `mark_initialized`) count as written.
+    pub fn enable_strict(&mut self, on_violation: OnViolation, reserved: Range<u32>) {
+        self.strict = Some(Strict { on_violation, reserved, initialized: vec![false; self.mem.len()] });
+    }
+
+    /// Marks bytes set up outside of the bus (font, program) as written for strict mode
+    pub fn mark_initialized(&mut self, range: Range<usize>) {
+        if let Some(strict) = &mut self.strict {
+            let end = usize::min(range.end, strict.initialized.len());
+
+            strict.initialized[usize::min(range.start, end)..end].fill(true);
+        }
+    }
+
+    /// Returns and clears the accesses strict mode logged
+    pub fn take_violations(&mut self) -> Vec<Violation> {
+        core::mem::take(&mut self.violations)
+    }
+
+    /// Reports an access in strict mode, if it is suspicious
+    fn check_strict(&mut self, addr: Address, masked: usize, access: Access) {
+        let is_device = matches!(self.mapping(masked), Some(Mapping { region: Region::Device(_), .. }));
+
+        let strict = match &mut self.strict {
+            Some(strict) => strict,
+            None => return,
+        };
+
+        let was_initialized = strict.initialized[masked];
+
+        if let Access::Write(_) = access {
+            strict.initialized[masked] = true;
+        }
+
+        let kind = match access {
+            _ if addr.0 as usize >= self.mem.len() => FaultKind::OutOfRange,
+            Access::Read if !is_device && !was_initialized => FaultKind::Uninitialized,
+            Access::Write(_) if strict.reserved.contains(&(masked as u32)) => FaultKind::Reserved,
+            _ => return,
+        };
+
+        let fault = BusFault { kind, addr: addr.0, access };
+
+        match strict.on_violation {
+            OnViolation::Log => self.violations.push(Violation { at: self.fetched_at, fault }),
+            OnViolation::Fault => self.raise(fault),
+        }
+    }
+
+    fn raise(&mut self, fault: BusFault) {
+        if self.fault.is_none() {
+            self.fault = Some(fault);
+        }
+    }
+
+    /// Data read (logged)
+    pub fn read_byte(&mut self, addr: Address) -> u8 {
+        let unmasked = addr;
+        let addr = addr.masked_address(&self.mem);
+
+        self.check_strict(unmasked, addr, Access::Read);
+
+        self.log.read[addr] = true;
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_read(addr);
+        }
+
+        match self.mapping_mut(addr) {
+            Some(Mapping { range, region: Region::Device(device) }) => device.read(addr as u32 - range.start),
+            _ => self.mem[addr],
+        }
+    }
+
+    /// Instruction fetch (not logged as a data read)
+    pub fn read_word(&self, addr: Address) -> u16 {
+        u16::from_be_bytes([self.peek_masked(addr.masked_address(&self.mem)), self.peek_masked(addr.masked_next_address(&self.mem))])
+    }
+
+    /// Fetch of the instruction at `addr`, recorded in the coverage map and checked in strict mode
+    pub fn fetch_opcode(&mut self, addr: Address) -> u16 {
+        self.fetched_at = addr.0;
+
+        self.check_strict(addr, addr.masked_address(&self.mem), Access::Read);
+        self.record_fetch(addr, true);
+        self.read_word(addr)
+    }
+
+    /// Fetch of an instruction's operand word, recorded in the coverage map
+    pub fn fetch_operand(&mut self, addr: Address) -> u16 {
+        self.record_fetch(addr, false);
+        self.read_word(addr)
+    }
+
+    fn record_fetch(&mut self, addr: Address, is_opcode: bool) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_fetch(addr.masked_address(&self.mem), addr.masked_next_address(&self.mem), is_opcode);
+        }
+    }
+
+    /// Read without side effects, for debuggers and viewers
+    pub fn peek_byte(&self, addr: Address) -> u8 {
+        self.peek_masked(addr.masked_address(&self.mem))
+    }
+
+    /// Data write (logged); writes rejected by a device or a read-only region raise a fault
+    pub fn write_byte(&mut self, addr: Address, data: u8) {
+        let unmasked = addr;
+        let addr = addr.masked_address(&self.mem);
+
+        self.check_strict(unmasked, addr, Access::Write(data));
+
+        self.log.written[addr] = true;
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_write(addr);
+        }
+
+        let accepted = match self.mapping_mut(addr) {
+            Some(Mapping { range, region: Region::Device(device) }) => device.write(addr as u32 - range.start, data),
+            Some(Mapping { region: Region::ReadOnly(on_write), .. }) => *on_write == OnWrite::Ignore,
+            None => {
+                self.mem[addr] = data;
+
+                true
+            }
+        };
+
+        if !accepted {
+            self.raise(BusFault { kind: FaultKind::Rejected, addr: unmasked.0, access: Access::Write(data) });
+        }
+    }
+
+    /// Write to RAM that bypasses devices and read-only regions, for debuggers and editors (not logged)
+    pub fn poke_byte(&mut self, addr: Address, data: u8) {
+        let addr = addr.masked_address(&self.mem);
+
+        self.mem[addr] = data;
+        self.mark_initialized(addr..addr + 1);
+    }
+
+    /// Returns and clears the pending bus fault
+    pub fn take_fault(&mut self) -> Option<BusFault> {
+        self.fault.take()
+    }
+
+    /// Direct read access for viewers and players (not logged)
+    pub fn memory(&self) -> &Memory {
+        &self.mem
+    }
+
+    /// Direct memory access for loaders (not logged)
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.mem
+    }
+
+    /// Starts recording which addresses are executed, read and written (see `coverage`)
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new(self.mem.len()));
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn access_log(&self) -> &AccessLog {
+        &self.log
+    }
+
+    pub fn access_log_mut(&mut self) -> &mut AccessLog {
+        &mut self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Register that only accepts writes at offset 0
+    struct Latch(u8);
+
+    impl BusDevice for Latch {
+        fn read(&mut self, _offset: u32) -> u8 {
+            self.0
+        }
+
+        fn write(&mut self, offset: u32, data: u8) -> bool {
+            if offset == 0 {
+                self.0 = data;
+            }
+
+            offset == 0
+        }
+
+        fn peek(&self, _offset: u32) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn device() {
+        let mut bus = Bus::new(Memory::new(0x1000));
+
+        bus.map(0xE00..0xE02, Box::new(Latch(0)));
+        bus.write_byte(Address::new(0xE00u32), 0x42);
+
+        assert_eq!((bus.read_byte(Address::new(0xE01u32)), bus.peek_byte(Address::new(0xE00u32))), (0x42, 0x42));
+        assert_eq!(bus.memory()[0xE00], 0);
+        assert_eq!(bus.take_fault(), None);
+
+        bus.write_byte(Address::new(0xE01u32), 0x17);
+
+        assert_eq!(bus.take_fault(), Some(BusFault { kind: FaultKind::Rejected, addr: 0xE01, access: Access::Write(0x17) }));
+        assert_eq!(bus.read_byte(Address::new(0xE00u32)), 0x42);
+    }
+
+    #[test]
+    fn protected_writes() {
+        let mut bus = Bus::new(Memory::new(0x1000));
+
+        bus.protect(0x200..0x300, OnWrite::Fault);
+        bus.protect(0x300..0x400, OnWrite::Ignore);
+
+        // Wraps around to 0x200; the fault keeps the address the program used
+        bus.write_byte(Address::new(0x1200u32), 0x42);
+
+        assert_eq!(bus.take_fault(), Some(BusFault { kind: FaultKind::Rejected, addr: 0x1200, access: Access::Write(0x42) }));
+        assert_eq!(bus.read_byte(Address::new(0x200u32)), 0);
+
+        bus.write_byte(Address::new(0x300u32), 0x42);
+
+        assert_eq!(bus.take_fault(), None);
+        assert_eq!(bus.read_byte(Address::new(0x300u32)), 0);
+
+        bus.write_byte(Address::new(0x400u32), 0x42);
+
+        assert_eq!((bus.take_fault(), bus.read_byte(Address::new(0x400u32))), (None, 0x42));
+    }
+}
@@ -0,0 +1,316 @@
+/// Memory, I/O and external signals seen by a CDP1802
+pub trait System {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, data: u8);
+
+    /// INP n (1-7): byte driven on the data bus by the selected device
+    fn input(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    /// OUT n (1-7)
+    fn output(&mut self, _port: u8, _data: u8) {}
+
+    /// Level of external flag EF1-EF4 (`flag` 1-4)
+    fn flag(&mut self, _flag: u8) -> bool {
+        false
+    }
+
+    /// Called whenever Q changes
+    fn set_q(&mut self, _q: bool) {}
+}
+
+/// RCA CDP1802 (COSMAC) CPU
+#[derive(Clone, Debug)]
+pub struct Cdp1802 {
+    /// Scratchpad registers R0-RF
+    pub r: [u16; 16],
+
+    /// Accumulator
+    pub d: u8,
+
+    /// Carry/borrow flag
+    pub df: bool,
+
+    /// Selects the program counter register
+    pub p: u8,
+
+    /// Selects the data pointer register
+    pub x: u8,
+
+    /// Holds X and P saved by an interrupt or MARK
+    pub t: u8,
+
+    /// Interrupt enable
+    pub ie: bool,
+
+    pub q: bool,
+
+    /// Set by IDL until an interrupt or DMA request
+    pub idle: bool,
+}
+
+impl Default for Cdp1802 {
+    /// State after a reset: P, X and R0 cleared, interrupts enabled
+    fn default() -> Self {
+        Self { r: [0; 16], d: 0, df: false, p: 0, x: 0, t: 0, ie: true, q: false, idle: false }
+    }
+}
+
+impl Cdp1802 {
+    /// Clock cycles per machine cycle
+    pub const CLOCKS_PER_CYCLE: u32 = 8;
+
+    fn fetch<S: System>(&mut self, sys: &mut S) -> u8 {
+        let p = self.p as usize;
+        let byte = sys.read(self.r[p]);
+
+        self.r[p] = self.r[p].wrapping_add(1);
+
+        byte
+    }
+
+    fn rx(&mut self) -> &mut u16 {
+        &mut self.r[self.x as usize]
+    }
+
+    /// M(R(X))
+    fn read_rx<S: System>(&self, sys: &mut S) -> u8 {
+        sys.read(self.r[self.x as usize])
+    }
+
+    /// D, DF = a + b + carry
+    fn add(&mut self, a: u8, b: u8, carry: bool) {
+        let sum = a as u16 + b as u16 + carry as u16;
+
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    /// D, DF = a - b - borrow (DF set when there is no borrow)
+    fn sub(&mut self, a: u8, b: u8, borrow: bool) {
+        self.add(a, !b, !borrow);
+    }
+
+    /// Short branch to the byte at R(P) within the current page, or skip it
+    fn short_branch<S: System>(&mut self, sys: &mut S, condition: bool) {
+        let p = self.p as usize;
+
+        if condition {
+            let target = sys.read(self.r[p]);
+
+            self.r[p] = (self.r[p] & 0xFF00) | target as u16;
+        } else {
+            self.r[p] = self.r[p].wrapping_add(1);
+        }
+    }
+
+    /// Long branch to the word at R(P), or skip it
+    fn long_branch<S: System>(&mut self, sys: &mut S, condition: bool) {
+        let p = self.p as usize;
+
+        if condition {
+            let (high, low) = (sys.read(self.r[p]), sys.read(self.r[p].wrapping_add(1)));
+
+            self.r[p] = u16::from_be_bytes([high, low]);
+        } else {
+            self.r[p] = self.r[p].wrapping_add(2);
+        }
+    }
+
+    /// Skips the next two bytes
+    fn long_skip(&mut self, condition: bool) {
+        if condition {
+            let p = self.p as usize;
+
+            self.r[p] = self.r[p].wrapping_add(2);
+        }
+    }
+
+    /// Takes an interrupt if enabled: saves X and P in T, disables interrupts and continues with P = 1, X = 2
+    ///
+    /// Returns true if the interrupt was taken.
+    pub fn interrupt(&mut self) -> bool {
+        if !self.ie {
+            return false;
+        }
+
+        self.t = self.x << 4 | self.p;
+        (self.ie, self.p, self.x, self.idle) = (false, 1, 2, false);
+
+        true
+    }
+
+    /// DMA output cycle: returns the byte at R0 and advances R0
+    pub fn dma_out<S: System>(&mut self, sys: &mut S) -> u8 {
+        let byte = sys.read(self.r[0]);
+
+        self.r[0] = self.r[0].wrapping_add(1);
+        self.idle = false;
+
+        byte
+    }
+
+    /// Executes one instruction, returning the number of machine cycles it took
+    pub fn step<S: System>(&mut self, sys: &mut S) -> u32 {
+        if self.idle {
+            return 1;
+        }
+
+        let opcode = self.fetch(sys);
+        let n = (opcode & 0xF) as usize;
+
+        match opcode >> 4 {
+            0x0 if n == 0 => self.idle = true,
+            0x0 => self.d = sys.read(self.r[n]),
+            0x1 => self.r[n] = self.r[n].wrapping_add(1),
+            0x2 => self.r[n] = self.r[n].wrapping_sub(1),
+            0x3 => {
+                let condition = match n & 0x7 {
+                    0x0 => true,
+                    0x1 => self.q,
+                    0x2 => self.d == 0,
+                    0x3 => self.df,
+                    flag => sys.flag(flag as u8 - 3),
+                };
+
+                // 38-3F branch on the inverted condition (38 is SKP)
+                self.short_branch(sys, condition != (n >= 0x8));
+            }
+            0x4 => {
+                self.d = sys.read(self.r[n]);
+                self.r[n] = self.r[n].wrapping_add(1);
+            }
+            0x5 => sys.write(self.r[n], self.d),
+            0x6 if n == 0 => *self.rx() = self.rx().wrapping_add(1),
+            0x6 if n < 0x8 => {
+                let data = self.read_rx(sys);
+
+                sys.output(n as u8, data);
+                *self.rx() = self.rx().wrapping_add(1);
+            }
+            // 68 is not a CDP1802 instruction
+            0x6 if n == 0x8 => {}
+            0x6 => {
+                let data = sys.input(n as u8 - 8);
+
+                sys.write(*self.rx(), data);
+                self.d = data;
+            }
+            0x7 => match n {
+                // RET, DIS
+                0x0 | 0x1 => {
+                    let xp = self.read_rx(sys);
+
+                    *self.rx() = self.rx().wrapping_add(1);
+                    (self.x, self.p, self.ie) = (xp >> 4, xp & 0xF, n == 0);
+                }
+                // LDXA
+                0x2 => {
+                    self.d = self.read_rx(sys);
+                    *self.rx() = self.rx().wrapping_add(1);
+                }
+                // STXD
+                0x3 => {
+                    sys.write(*self.rx(), self.d);
+                    *self.rx() = self.rx().wrapping_sub(1);
+                }
+                // ADC, SDB, SMB
+                0x4 => self.add(self.read_rx(sys), self.d, self.df),
+                0x5 => self.sub(self.read_rx(sys), self.d, !self.df),
+                0x7 => self.sub(self.d, self.read_rx(sys), !self.df),
+                // SHRC
+                0x6 => (self.d, self.df) = ((self.d >> 1) | (self.df as u8) << 7, self.d & 1 != 0),
+                // SAV
+                0x8 => sys.write(*self.rx(), self.t),
+                // MARK
+                0x9 => {
+                    self.t = self.x << 4 | self.p;
+                    sys.write(self.r[2], self.t);
+                    self.x = self.p;
+                    self.r[2] = self.r[2].wrapping_sub(1);
+                }
+                // REQ, SEQ
+                0xA | 0xB => {
+                    self.q = n == 0xB;
+                    sys.set_q(self.q);
+                }
+                // ADCI, SDBI, SMBI
+                0xC => {
+                    let m = self.fetch(sys);
+
+                    self.add(m, self.d, self.df);
+                }
+                0xD => {
+                    let m = self.fetch(sys);
+
+                    self.sub(m, self.d, !self.df);
+                }
+                0xF => {
+                    let m = self.fetch(sys);
+
+                    self.sub(self.d, m, !self.df);
+                }
+                // SHLC
+                _ => (self.d, self.df) = ((self.d << 1) | self.df as u8, self.d & 0x80 != 0),
+            },
+            0x8 => self.d = self.r[n] as u8,
+            0x9 => self.d = (self.r[n] >> 8) as u8,
+            0xA => self.r[n] = (self.r[n] & 0xFF00) | self.d as u16,
+            0xB => self.r[n] = (self.r[n] & 0x00FF) | (self.d as u16) << 8,
+            0xC => {
+                match n {
+                    // NOP
+                    0x4 => {}
+                    // LSNQ, LSNZ, LSNF, LSKP
+                    0x5 => self.long_skip(!self.q),
+                    0x6 => self.long_skip(self.d != 0),
+                    0x7 => self.long_skip(!self.df),
+                    0x8 => self.long_skip(true),
+                    // LSIE, LSQ, LSZ, LSDF
+                    0xC => self.long_skip(self.ie),
+                    0xD => self.long_skip(self.q),
+                    0xE => self.long_skip(self.d == 0),
+                    0xF => self.long_skip(self.df),
+                    _ => {
+                        let condition = match n & 0x3 {
+                            0x0 => true,
+                            0x1 => self.q,
+                            0x2 => self.d == 0,
+                            _ => self.df,
+                        };
+
+                        // C0-C3 branch on the condition, C9-CB on its inverse
+                        self.long_branch(sys, condition != (n >= 0x8));
+                    }
+                }
+
+                return 3;
+            }
+            0xD => self.p = n as u8,
+            0xE => self.x = n as u8,
+            _ => {
+                // Fx operates on M(R(X)) for x < 8 and on the immediate byte for x >= 8, except SHR/SHL
+                let m = match n {
+                    0x6 | 0xE => 0,
+                    0x0..=0x7 => self.read_rx(sys),
+                    _ => self.fetch(sys),
+                };
+
+                match n & 0x7 {
+                    0x0 => self.d = m,
+                    0x1 => self.d |= m,
+                    0x2 => self.d &= m,
+                    0x3 => self.d ^= m,
+                    0x4 => self.add(m, self.d, false),
+                    0x5 => self.sub(m, self.d, false),
+                    0x6 if n == 0x6 => (self.d, self.df) = (self.d >> 1, self.d & 1 != 0),
+                    0x6 => (self.d, self.df) = (self.d << 1, self.d & 0x80 != 0),
+                    _ => self.sub(self.d, m, false),
+                }
+            }
+        }
+
+        2
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{Core, bus::memory::Memory, cpu::vipmap, display::Display, font::Font};
+
+use core::{fmt, ops::Range, str::FromStr};
+
+/// Behaviours that differ between Chip-8 interpreters
+///
+/// The defaults match the original COSMAC VIP interpreter.
+#[derive(Clone, Copy, Debug)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vx in place instead of shifting Vy into Vx
+    pub shift: bool,
+
+    /// Fx55/Fx65 leave I unchanged instead of incrementing it past the last register
+    pub load_store: bool,
+
+    /// Bnnn jumps to xnn + Vx instead of nnn + V0
+    pub jump: bool,
+
+    /// 8xy1/8xy2/8xy3 reset VF to 0
+    pub logic: bool,
+
+    /// Sprites are clipped at the screen edges instead of wrapping around
+    pub clip: bool,
+
+    /// Drawing waits for the next frame (vertical blank)
+    pub vblank: bool,
+
+    /// Arithmetic writes VF before the result, so that a result in VF overwrites the flag
+    pub vf_order: bool,
+}
+
+impl Quirks {
+    /// Original COSMAC VIP interpreter
+    pub const VIP: Quirks = Quirks {
+        shift: false,
+        load_store: false,
+        jump: false,
+        logic: true,
+        clip: true,
+        vblank: true,
+        vf_order: false,
+    };
+
+    /// SUPER-CHIP 1.1 on the HP 48
+    pub const SCHIP: Quirks = Quirks {
+        shift: true,
+        load_store: true,
+        jump: true,
+        logic: false,
+        clip: true,
+        vblank: false,
+        vf_order: false,
+    };
+
+    /// Octo's XO-CHIP
+    pub const XO_CHIP: Quirks = Quirks {
+        shift: false,
+        load_store: false,
+        jump: false,
+        logic: false,
+        clip: false,
+        vblank: false,
+        vf_order: false,
+    };
+
+    /// Named quirk sets of well-known interpreters
+    pub const PROFILES: [(&'static str, Quirks); 3] = [("VIP", Self::VIP), ("SCHIP", Self::SCHIP), ("XO-CHIP", Self::XO_CHIP)];
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::VIP
+    }
+}
+
+/// Chip-8 dialect
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Chip8,
+
+    /// VIPER extensions: comparisons, register ranges, relative branches, stop and port I/O
+    Chip8E,
+
+    /// VIPER I/O extensions on port 3
+    Chip8I,
+
+    /// RCA VP-590/VP-595 expansion: color zones, a second keypad and port I/O
+    Chip8X,
+
+    /// Two-page 64x64 display, recognized by the 0x1260 jump at the start of the program
+    Hires,
+
+    /// 256x192 display with a 256-color palette, blending and digitised sound, switched on with 0011
+    MegaChip,
+}
+
+impl Variant {
+    /// Address programs are loaded at and start from
+    pub fn load_address(&self) -> u16 {
+        match self {
+            // The CHIP-8X interpreter is larger than the original one
+            Variant::Chip8X => 0x300,
+            _ => Core::ROM_START as u16,
+        }
+    }
+
+    /// Display width and height in pixels at reset
+    pub fn display_size(&self) -> (usize, usize) {
+        match self {
+            Variant::Hires => (Display::WIDTH, 2 * Display::HEIGHT),
+            _ => (Display::WIDTH, Display::HEIGHT),
+        }
+    }
+
+    /// Largest display width and height the program can switch to
+    pub fn screen_size(&self) -> (usize, usize) {
+        match self {
+            Variant::MegaChip => (Display::MEGA_WIDTH, Display::MEGA_HEIGHT),
+            _ => self.display_size(),
+        }
+    }
+
+    /// Memory size in bytes
+    pub fn memory_size(&self) -> usize {
+        match self {
+            // I is 24-bit, which lets programs carry bitmaps and sound samples
+            Variant::MegaChip => 0x100_0000,
+            _ => Memory::SIZE,
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is = |names: &[&str]| names.iter().any(|name| s.eq_ignore_ascii_case(name));
+
+        if is(&["chip8", "chip-8"]) {
+            Ok(Variant::Chip8)
+        } else if is(&["chip8e", "chip-8e"]) {
+            Ok(Variant::Chip8E)
+        } else if is(&["chip8i", "chip-8i"]) {
+            Ok(Variant::Chip8I)
+        } else if is(&["chip8x", "chip-8x"]) {
+            Ok(Variant::Chip8X)
+        } else if is(&["hires", "chip8-hires", "chip-8-hires"]) {
+            Ok(Variant::Hires)
+        } else if is(&["megachip", "megachip8", "mega-chip"]) {
+            Ok(Variant::MegaChip)
+        } else {
+            Err(ConfigError::UnknownVariant)
+        }
+    }
+}
+
+/// RPL user flags on the HP 48, where SUPER-CHIP's Fx75/Fx85 save and restore V0-Vx
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RplFlags {
+    /// Fx75/Fx85 are not instructions
+    #[default]
+    None,
+
+    /// 8 flags, V0-V7
+    Schip,
+
+    /// XO-CHIP's 16 flags, V0-VF
+    XoChip,
+}
+
+impl RplFlags {
+    /// Number of flags
+    pub fn count(&self) -> usize {
+        match self {
+            RplFlags::None => 0,
+            RplFlags::Schip => 8,
+            RplFlags::XoChip => 16,
+        }
+    }
+}
+
+impl FromStr for RplFlags {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is = |names: &[&str]| names.iter().any(|name| s.eq_ignore_ascii_case(name));
+
+        if is(&["none", "0"]) {
+            Ok(RplFlags::None)
+        } else if is(&["schip", "super-chip", "8"]) {
+            Ok(RplFlags::Schip)
+        } else if is(&["xochip", "xo-chip", "16"]) {
+            Ok(RplFlags::XoChip)
+        } else {
+            Err(ConfigError::UnknownRplFlags)
+        }
+    }
+}
+
+/// Invalid machine configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    LoadAddressOutOfRange(u16),
+    EntryPointOutOfRange(u16),
+
+    /// Program does not fit between the load address and the end of memory
+    RomTooLarge { len: usize, max: usize },
+
+    UnknownVariant,
+    UnknownFont,
+    UnknownRplFlags,
+
+    /// Font does not fit between the font address and the end of memory
+    FontOutOfRange(u16),
+
+    /// Font and program share addresses
+    FontOverlapsProgram(u16),
+
+    /// The VIP memory map only applies to the 64x32 CHIP-8 display in 4K of memory
+    VipMemoryMapUnsupported,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::LoadAddressOutOfRange(addr) => write!(f, "load address {:#05X} is outside of memory", addr),
+            ConfigError::EntryPointOutOfRange(addr) => write!(f, "entry point {:#05X} is outside of memory", addr),
+            ConfigError::RomTooLarge { len, max } => write!(f, "ROM is {} bytes but only {} fit after the load address", len, max),
+            ConfigError::UnknownVariant => write!(f, "unknown variant, expected chip8, chip8e, chip8i, chip8x, hires or megachip"),
+            ConfigError::UnknownFont => write!(f, "unknown font, expected vip, eti660, dream6800, fishnchips or octo"),
+            ConfigError::UnknownRplFlags => write!(f, "unknown RPL flags, expected none, schip or xochip"),
+            ConfigError::FontOutOfRange(addr) => write!(f, "font at {:#05X} does not fit in memory", addr),
+            ConfigError::FontOverlapsProgram(addr) => write!(f, "font at {:#05X} overlaps the program", addr),
+            ConfigError::VipMemoryMapUnsupported => write!(f, "the VIP memory map is only available for chip8"),
+        }
+    }
+}
+
+/// Machine configuration
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Instructions executed per 60 Hz frame
+    pub steps: usize,
+
+    pub quirks: Quirks,
+
+    /// 0RGB colors of unlit and lit pixels
+    pub palette: [u32; 2],
+
+    /// Address the program is copied to (0x600 on the ETI 660)
+    pub load_address: u16,
+
+    /// Initial PC
+    pub entry_point: u16,
+
+    pub variant: Variant,
+
+    /// Lets `detect_variant` switch CHIP-8 programs starting with `Core::HIRES_SIGNATURE` to `Variant::Hires`; cleared
+    /// by `set_variant`, so that a variant given explicitly sticks
+    pub detect_hires: bool,
+
+    /// Keeps the call stack (0xEA0) and display (0xF00) in memory where the VIP interpreter has them, for programs
+    /// that access them directly
+    pub vip_memory_map: bool,
+
+    /// Digit glyphs, and the address they are copied to (some programs expect them at 0x000)
+    pub font: Font,
+    pub font_address: u16,
+
+    /// Enables Fx75/Fx85 with this many flags
+    pub rpl_flags: RplFlags,
+}
+
+impl Config {
+    /// Switches to another dialect, moving the load address and entry point to the ones it expects
+    ///
+    /// MegaChip builds on SUPER-CHIP, so it also gets the 8 SUPER-CHIP RPL user flags unless some were set. The variant
+    /// is no longer detected from the program.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.detect_hires = false;
+        self.load_address = variant.load_address();
+        self.entry_point = variant.load_address();
+
+        if variant == Variant::MegaChip && self.rpl_flags == RplFlags::None {
+            self.rpl_flags = RplFlags::Schip;
+        }
+    }
+
+    /// Switches to `Variant::Hires` if the program starts with `Core::HIRES_SIGNATURE`, unless a variant was set or the
+    /// VIP memory map is on
+    pub fn detect_variant(&mut self, rom: &[u8]) {
+        if self.detect_hires && self.variant == Variant::Chip8 && !self.vip_memory_map && rom.starts_with(&Core::HIRES_SIGNATURE) {
+            self.variant = Variant::Hires;
+        }
+    }
+
+    /// End of the memory available to programs
+    fn program_end(&self) -> usize {
+        if self.vip_memory_map { vipmap::VIP_STACK as usize } else { self.variant.memory_size() }
+    }
+
+    /// Largest program that fits at the load address
+    pub fn max_rom_size(&self) -> usize {
+        self.program_end().saturating_sub(self.load_address as usize)
+    }
+
+    /// Addresses the font takes in memory
+    pub fn font_range(&self) -> Range<usize> {
+        self.font_address as usize..self.font_address as usize + Font::SIZE
+    }
+
+    /// Checks that the load address and entry point lie in memory, that a program of `rom_len` bytes fits and that the
+    /// font fits next to it
+    pub fn validate(&self, rom_len: usize) -> Result<(), ConfigError> {
+        if self.load_address as usize >= self.variant.memory_size() {
+            return Err(ConfigError::LoadAddressOutOfRange(self.load_address));
+        }
+
+        if self.entry_point as usize >= self.variant.memory_size() {
+            return Err(ConfigError::EntryPointOutOfRange(self.entry_point));
+        }
+
+        if self.vip_memory_map && self.variant != Variant::Chip8 {
+            return Err(ConfigError::VipMemoryMapUnsupported);
+        }
+
+        if rom_len > self.max_rom_size() {
+            return Err(ConfigError::RomTooLarge { len: rom_len, max: self.max_rom_size() });
+        }
+
+        if self.font_range().end > self.program_end() {
+            return Err(ConfigError::FontOutOfRange(self.font_address));
+        }
+
+        let program = self.load_address as usize..self.load_address as usize + rom_len;
+
+        if self.font_range().start < program.end && program.start < self.font_range().end {
+            return Err(ConfigError::FontOverlapsProgram(self.font_address));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            steps: 11,
+            quirks: Quirks::default(),
+            palette: [0x00000000, 0x00FFFFFF],
+            load_address: Core::ROM_START as u16,
+            entry_point: Core::ROM_START as u16,
+            variant: Variant::default(),
+            detect_hires: true,
+            vip_memory_map: false,
+            font: Font::default(),
+            font_address: Core::FONT_START as u16,
+            rpl_flags: RplFlags::default(),
+        }
+    }
+}
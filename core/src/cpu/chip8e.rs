@@ -0,0 +1,170 @@
+use crate::{
+    bus::Address,
+    cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode},
+};
+
+/// CHIP-8E instructions, matched before the base set (BBnn and BFnn replace those cases of Bnnn)
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 14] = [
+    OpcodeDesc(0x00ED, 0xFFFF, Cpu::stop),
+    OpcodeDesc(0x00F2, 0xFFFF, Cpu::nop),
+    OpcodeDesc(0x0151, 0xFFFF, Cpu::wait_dt),
+    OpcodeDesc(0x0188, 0xFFFF, Cpu::skip_always),
+    OpcodeDesc(0x5001, 0xF00F, Cpu::sgt),
+    OpcodeDesc(0x5002, 0xF00F, Cpu::ldi_range),
+    OpcodeDesc(0x5003, 0xF00F, Cpu::ldv_range),
+    OpcodeDesc(0xBB00, 0xFF00, Cpu::jp_back),
+    OpcodeDesc(0xBF00, 0xFF00, Cpu::jp_forward),
+    OpcodeDesc(0xF003, 0xF0FF, Cpu::out),
+    OpcodeDesc(0xF01B, 0xF0FF, Cpu::skip_bytes),
+    OpcodeDesc(0xF04F, 0xF0FF, Cpu::lddt_wait),
+    OpcodeDesc(0xF0E3, 0xF0FF, Cpu::inp),
+    OpcodeDesc(0xF0E7, 0xF0FF, Cpu::inp_now),
+];
+
+impl Cpu {
+    /// Registers x to y in the order they are stored (backwards if x > y)
+    fn register_range(opcode: Opcode) -> impl Iterator<Item = usize> {
+        let (x, y) = (opcode.x(), opcode.y());
+        let (low, high) = (usize::min(x, y), usize::max(x, y));
+
+        (low..=high).map(move |i| if x <= y { i } else { x + y - i })
+    }
+
+    /// Branch back nn bytes from the next instruction
+    fn jp_back(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.pc() = self.pc().wrapping_sub(opcode.kk() as u16);
+
+        None
+    }
+
+    /// Branch forward nn bytes from the next instruction
+    fn jp_forward(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.pc() = self.pc().wrapping_add(opcode.kk() as u16);
+
+        None
+    }
+
+    /// [I] = Vx-Vy
+    fn ldi_range(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+
+        for (offset, i) in Self::register_range(opcode).enumerate() {
+            let vi = *self.v(i);
+
+            self.bus.write_byte(Address::new(index.wrapping_add(offset as u32)), vi);
+        }
+
+        self.advance_i(opcode.x().abs_diff(opcode.y()));
+
+        None
+    }
+
+    /// Vx-Vy = [I]
+    fn ldv_range(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+
+        for (offset, i) in Self::register_range(opcode).enumerate() {
+            *self.v(i) = self.bus.read_byte(Address::new(index.wrapping_add(offset as u32)));
+        }
+
+        self.advance_i(opcode.x().abs_diff(opcode.y()));
+
+        None
+    }
+
+    /// Delay timer = Vx, then wait until it is 0
+    fn lddt_wait(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        if !self.delay_wait {
+            *self.dt() = *self.v(opcode.x());
+            self.delay_wait = true;
+        }
+
+        if *self.dt() != 0 {
+            self.regfile.rewind_pc();
+        } else {
+            self.delay_wait = false;
+        }
+
+        None
+    }
+
+    /// No operation
+    fn nop(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        None
+    }
+
+    /// Skip if Vx > Vy
+    fn sgt(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let condition = *self.v(opcode.x()) > *self.v(opcode.y());
+
+        self.skip(condition);
+
+        None
+    }
+
+    /// Skip next instruction
+    fn skip_always(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.skip(true);
+
+        None
+    }
+
+    /// Skip Vx bytes
+    fn skip_bytes(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.pc() = self.pc().wrapping_add(*self.v(opcode.x()) as u16);
+
+        None
+    }
+
+    /// Stop: stays on this instruction for good
+    fn stop(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.regfile.rewind_pc();
+
+        None
+    }
+
+    /// Wait until the delay timer is 0
+    fn wait_dt(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        if *self.dt() != 0 {
+            self.regfile.rewind_pc();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Core, config::{Config, Variant}};
+
+    #[test]
+    fn delay_and_wait() {
+        let mut config = Config::default();
+
+        config.set_variant(Variant::Chip8E);
+
+        // V0 = 3, Fx4F, V1 = 1, STOP
+        let mut core = Core::with_config(&[0x60, 0x03, 0xF0, 0x4F, 0x61, 0x01, 0x00, 0xED], 0, config);
+
+        // The timer is set in the first frame and ticks down at the start of the next ones
+        for frame in 0..4 {
+            core.frame();
+
+            assert_eq!(core.regfile().gprs[1], (frame == 3) as u8, "frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn input_without_strobe() {
+        let mut config = Config::default();
+
+        config.set_variant(Variant::Chip8E);
+
+        // V0 = 5, V0 = port (nothing latched), STOP
+        let mut core = Core::with_config(&[0x60, 0x05, 0xF0, 0xE7, 0x00, 0xED], 0, config);
+
+        core.frame();
+
+        assert_eq!(core.regfile().gprs[0], 0);
+    }
+}
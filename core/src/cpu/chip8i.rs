@@ -0,0 +1,17 @@
+use crate::cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode};
+
+/// CHIP-8I instructions (port 3 I/O), matched before the base set
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 3] = [
+    OpcodeDesc(0xF003, 0xF0FF, Cpu::out),
+    OpcodeDesc(0xF0E3, 0xF0FF, Cpu::inp),
+    OpcodeDesc(0xF0E7, 0xF0FF, Cpu::inp_now),
+];
+
+impl Cpu {
+    /// Vx = byte on the input port, without waiting for a strobe (0 if nothing was latched)
+    pub(super) fn inp_now(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) = self.port.take_input().unwrap_or(0);
+
+        None
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode},
+    display::Display,
+};
+
+/// CHIP-8X instructions, matched before the base set (BxyN replaces Bnnn)
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 7] = [
+    OpcodeDesc(0x02A0, 0xFFFF, Cpu::bgnd),
+    OpcodeDesc(0x5001, 0xF00F, Cpu::add_bcd),
+    OpcodeDesc(0xB000, 0xF000, Cpu::col),
+    OpcodeDesc(0xE0F2, 0xF0FF, Cpu::skp2),
+    OpcodeDesc(0xE0F5, 0xF0FF, Cpu::sknp2),
+    OpcodeDesc(0xF0F8, 0xF0FF, Cpu::out),
+    OpcodeDesc(0xF0FB, 0xF0FF, Cpu::inp),
+];
+
+/// Byte-wide expansion port (FxF8 writes it, FxFB waits for a byte from it; Fx03 and FxE3 on CHIP-8E and CHIP-8I)
+///
+/// On the VP-595 the output sets the tone frequency; other expansion boards can be wired up by the frontend.
+#[derive(Default)]
+pub struct IoPort {
+    output: u8,
+    input: Option<u8>,
+}
+
+impl IoPort {
+    /// Returns the last byte written by the program
+    pub fn output(&self) -> u8 {
+        self.output
+    }
+
+    /// Latches a byte for the next FxFB
+    pub fn set_input(&mut self, value: u8) {
+        self.input = Some(value);
+    }
+
+    /// Consumes the latched byte
+    pub(super) fn take_input(&mut self) -> Option<u8> {
+        self.input.take()
+    }
+}
+
+impl Cpu {
+    /// Vx = Vx + Vy, adding the low 3 bits of each nibble separately
+    fn add_bcd(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let vy = *self.v(opcode.y());
+        let vx = self.v(opcode.x());
+
+        *vx = (((*vx & 0x70) + (vy & 0x70)) & 0x70) | (((*vx & 0x07) + (vy & 0x07)) & 0x07);
+
+        None
+    }
+
+    /// Cycle background color
+    fn bgnd(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.display.cycle_background();
+
+        None
+    }
+
+    /// Set foreground color V(y+1) for zones: 8x4 zones with N == 0, otherwise 8x1 zones over N rows
+    ///
+    /// The low nibble of Vx is the first zone column and the high nibble the number of extra columns. With N == 0, Vy
+    /// selects 4-row zones the same way; otherwise Vy is the first pixel row.
+    fn col(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let (vx, vy, color) = (
+            *self.v(opcode.x()) as usize,
+            *self.v(opcode.y()) as usize,
+            *self.v((opcode.y() + 1) % 16),
+        );
+
+        let columns = (vx & 0xF)..=(vx & 0xF) + (vx >> 4);
+
+        let rows = match opcode.n() {
+            0 => 4 * (vy & 0xF)..4 * ((vy & 0xF) + (vy >> 4) + 1),
+            n => vy..vy + n,
+        };
+
+        for y in rows {
+            for column in columns.clone() {
+                self.display.set_zone_color(column % Display::ZONE_COLUMNS, y, color);
+            }
+        }
+
+        None
+    }
+
+    /// Vx = byte from the expansion port
+    pub(super) fn inp(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        match self.port.take_input() {
+            Some(value) => {
+                *self.v(opcode.x()) = value;
+
+                None
+            }
+            None => {
+                self.regfile.rewind_pc();
+
+                Some(CpuEvent::WaitForInput)
+            }
+        }
+    }
+
+    /// Expansion port = Vx
+    pub(super) fn out(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.port.output = *self.v(opcode.x());
+
+        None
+    }
+
+    /// Skip if key x is pressed on the second keypad
+    fn skp2(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let key = *self.v(opcode.x()) as usize;
+        let is_key_pressed = self.second_keypad.is_key_pressed(key);
+
+        self.skip(is_key_pressed);
+
+        None
+    }
+
+    /// Skip if key x is not pressed on the second keypad
+    fn sknp2(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let key = *self.v(opcode.x()) as usize;
+        let is_key_pressed = self.second_keypad.is_key_pressed(key);
+
+        self.skip(!is_key_pressed);
+
+        None
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    bus::Address,
+    config::Quirks,
+    cpu::{Cpu, CpuEvent, opcode::Opcode, regfile::RegFile},
+    display::Display,
+    keypad::Keypad,
+};
+
+/// Handler of an instruction added by an embedder
+///
+/// It runs with PC already advanced past the instruction. Returning an event has the same effect as for the built-in
+/// instructions (e.g. `CpuEvent::Draw` ends the frame with the vblank quirk).
+pub type CustomHandler = fn(&mut Context, Opcode) -> Option<CpuEvent>;
+
+/// Custom instruction (opcode pattern, mask, handler), matched when `opcode & mask == pattern`
+#[derive(Clone, Copy)]
+pub struct CustomOpcode {
+    pub pattern: u16,
+    pub mask: u16,
+    pub handler: CustomHandler,
+}
+
+/// Error of `Context::builtin` when no built-in instruction matches the opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoBuiltin;
+
+/// What a custom instruction can see and change: registers, memory, display, keypad and stack
+pub struct Context<'a> {
+    cpu: &'a mut Cpu,
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn new(cpu: &'a mut Cpu) -> Self {
+        Self { cpu }
+    }
+
+    pub fn regfile(&self) -> &RegFile {
+        &self.cpu.regfile
+    }
+
+    pub fn regfile_mut(&mut self) -> &mut RegFile {
+        &mut self.cpu.regfile
+    }
+
+    pub fn v(&self, x: usize) -> u8 {
+        self.cpu.regfile.gprs[x % 16]
+    }
+
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.cpu.regfile.gprs[x % 16] = value;
+    }
+
+    /// Memory read through the bus (logged, seen by mapped devices)
+    pub fn read(&mut self, addr: u32) -> u8 {
+        self.cpu.bus.read_byte(Address::new(addr))
+    }
+
+    /// Memory write through the bus (logged, seen by mapped devices, may fault)
+    pub fn write(&mut self, addr: u32, data: u8) {
+        self.cpu.bus.write_byte(Address::new(addr), data);
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.cpu.display
+    }
+
+    pub fn display_mut(&mut self) -> &mut Display {
+        &mut self.cpu.display
+    }
+
+    pub fn keypad(&self) -> &Keypad {
+        &self.cpu.keypad
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.cpu.quirks
+    }
+
+    /// Skips the next instruction if `condition` is true
+    pub fn skip(&mut self, condition: bool) {
+        self.cpu.skip(condition);
+    }
+
+    /// Pushes a return address, or returns false if the stack is full
+    pub fn push(&mut self, addr: u16) -> bool {
+        if self.cpu.stack.is_full() {
+            return false;
+        }
+
+        self.cpu.stack.push(&mut self.cpu.bus, addr);
+
+        true
+    }
+
+    /// Pops a return address, or returns None if the stack is empty
+    pub fn pop(&mut self) -> Option<u16> {
+        if self.cpu.stack.depth() == 0 {
+            return None;
+        }
+
+        Some(self.cpu.stack.pop(&mut self.cpu.bus))
+    }
+
+    pub fn next_random(&mut self) -> u8 {
+        self.cpu.rng.next_u8()
+    }
+
+    /// Runs the instruction the custom one replaced, for handlers that only change some of its cases
+    ///
+    /// Fails with `NoBuiltin`, without running anything, if no built-in instruction matches the opcode.
+    pub fn builtin(&mut self, opcode: Opcode) -> Result<Option<CpuEvent>, NoBuiltin> {
+        let handler = self.cpu.matcher.find_opcode(opcode.raw()).ok_or(NoBuiltin)?;
+
+        Ok(handler(self.cpu, opcode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Core;
+
+    /// 00EE that sets V0 instead of returning with an empty stack
+    fn ret_or_flag(ctx: &mut Context, _opcode: Opcode) -> Option<CpuEvent> {
+        match ctx.pop() {
+            Some(addr) => ctx.regfile_mut().pc = addr,
+            None => ctx.set_v(0, 1),
+        }
+
+        None
+    }
+
+    /// 5xyn that sets V1 for the cases no built-in instruction handles
+    fn se_or_flag(ctx: &mut Context, opcode: Opcode) -> Option<CpuEvent> {
+        ctx.builtin(opcode).unwrap_or_else(|NoBuiltin| {
+            ctx.set_v(1, 1);
+
+            None
+        })
+    }
+
+    /// 2nnn that sets V2 instead of overflowing the stack
+    fn call_or_flag(ctx: &mut Context, opcode: Opcode) -> Option<CpuEvent> {
+        let pc = ctx.regfile().pc;
+
+        if ctx.push(pc) {
+            ctx.regfile_mut().pc = opcode.nnn();
+        } else {
+            ctx.set_v(2, 1);
+        }
+
+        None
+    }
+
+    #[test]
+    fn empty_stack_and_unknown_opcodes() {
+        // Calls 208, which returns to 202; the 00EE there has nothing to return to and 5017 no built-in instruction
+        let mut core = Core::new(&[0x22, 0x08, 0x00, 0xEE, 0x50, 0x17, 0x12, 0x06, 0x00, 0xEE], 0);
+
+        core.register_opcode(CustomOpcode { pattern: 0x00EE, mask: 0xFFFF, handler: ret_or_flag });
+        core.register_opcode(CustomOpcode { pattern: 0x5000, mask: 0xF000, handler: se_or_flag });
+        core.frame();
+
+        assert_eq!((core.regfile().gprs[0], core.regfile().gprs[1]), (1, 1));
+        assert_eq!(core.regfile().pc, 0x206);
+    }
+
+    #[test]
+    fn full_stack() {
+        // Calls itself until the stack is full, then falls through to the jump below
+        let mut core = Core::new(&[0x22, 0x00, 0x12, 0x02], 0);
+
+        core.register_opcode(CustomOpcode { pattern: 0x2000, mask: 0xF000, handler: call_or_flag });
+        core.frame();
+        core.frame();
+
+        assert_eq!(core.regfile().gprs[2], 1);
+        assert_eq!(core.regfile().pc, 0x202);
+    }
+}
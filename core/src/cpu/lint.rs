@@ -0,0 +1,202 @@
+use crate::{
+    bus::Address,
+    config::{Quirks, Variant},
+    cpu::{Cpu, opcode::Opcode, regfile::VF},
+};
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// Quirk whose setting changed what an instruction did
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quirk {
+    /// 8xy6/8xyE with Vx != Vy
+    Shift,
+
+    /// I used after Fx55/Fx65 moved it
+    LoadStore,
+
+    /// Bxnn with Vx != V0
+    Jump,
+
+    /// Sprite pixels past the screen edge
+    Clip,
+
+    /// 8Fy4-8FyE, whose result and flag both go to VF
+    VfOrder,
+}
+
+impl Quirk {
+    pub const ALL: [Quirk; 5] = [Quirk::Shift, Quirk::LoadStore, Quirk::Jump, Quirk::Clip, Quirk::VfOrder];
+
+    /// Setting of this quirk in `quirks`
+    pub fn get(&self, quirks: &Quirks) -> bool {
+        match self {
+            Quirk::Shift => quirks.shift,
+            Quirk::LoadStore => quirks.load_store,
+            Quirk::Jump => quirks.jump,
+            Quirk::Clip => quirks.clip,
+            Quirk::VfOrder => quirks.vf_order,
+        }
+    }
+
+    /// Name as used in the ROM database
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quirk::Shift => "shift",
+            Quirk::LoadStore => "load-store",
+            Quirk::Jump => "jump",
+            Quirk::Clip => "clip",
+            Quirk::VfOrder => "vf-order",
+        }
+    }
+
+    /// What the program did that depends on the quirk
+    pub fn description(&self) -> &'static str {
+        match self {
+            Quirk::Shift => "8xy6/8xyE shifts with x != y and Vx != Vy",
+            Quirk::LoadStore => "I is used after Fx55/Fx65 without being set again",
+            Quirk::Jump => "Bxnn jumps with Vx != V0",
+            Quirk::Clip => "sprites are drawn across the screen edge",
+            Quirk::VfOrder => "arithmetic with VF as the destination",
+        }
+    }
+}
+
+/// Records instructions whose result depends on an ambiguous quirk, while the program runs
+///
+/// Only what actually happens counts: a shift with x != y is harmless while Vx == Vy, a sprite at the edge only
+/// matters if lit pixels cross it. The logic and vblank quirks are not checked.
+#[derive(Default)]
+pub struct QuirkLinter {
+    /// Times each quirk mattered, by quirk and instruction address
+    findings: BTreeMap<(Quirk, u16), u64>,
+
+    /// Fx55/Fx65 that last moved I, until I is read or set again
+    index_moved_at: Option<u16>,
+}
+
+impl QuirkLinter {
+    fn record(&mut self, quirk: Quirk, at: u16) {
+        *self.findings.entry((quirk, at)).or_default() += 1;
+    }
+
+    /// Follows I through an instruction, recording uses of I that the load/store quirk changes
+    fn track_index(&mut self, pc: u16, opcode: Opcode, variant: Variant) {
+        let is_range = variant == Variant::Chip8E && opcode.raw() & 0xF00E == 0x5002;
+
+        let (uses, moves, sets) = match (opcode.raw() >> 12, opcode.kk()) {
+            (0xA, _) => (false, false, true),
+            (0xD, _) => (true, false, false),
+            (0xF, 0x1E) | (0xF, 0x33) => (true, false, false),
+            (0xF, 0x29) => (false, false, true),
+            (0xF, 0x55) | (0xF, 0x65) => (true, true, false),
+            _ => (is_range, is_range, false),
+        };
+
+        if let (true, Some(at)) = (uses, self.index_moved_at) {
+            self.record(Quirk::LoadStore, at);
+        }
+
+        if moves {
+            self.index_moved_at = Some(pc);
+        } else if uses || sets {
+            self.index_moved_at = None;
+        }
+    }
+
+    /// Instructions that depended on a quirk: (quirk, address, times), by quirk and address
+    pub fn findings(&self) -> impl Iterator<Item = (Quirk, u16, u64)> + '_ {
+        self.findings.iter().map(|(&(quirk, at), &count)| (quirk, at, count))
+    }
+
+    pub fn depends_on(&self, quirk: Quirk) -> bool {
+        self.findings.keys().any(|&(found, _)| found == quirk)
+    }
+
+    /// Names of the profiles in `Quirks::PROFILES` that agree with `quirks` on every quirk the program depended on
+    ///
+    /// This assumes the program behaved as intended with `quirks`.
+    pub fn matching_profiles(&self, quirks: &Quirks) -> Vec<&'static str> {
+        Quirks::PROFILES
+            .iter()
+            .filter(|(_, profile)| Quirk::ALL.iter().all(|quirk| !self.depends_on(*quirk) || quirk.get(profile) == quirk.get(quirks)))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl Cpu {
+    /// Checks the instruction about to run at `pc` for quirk dependence
+    pub(super) fn lint(&mut self, pc: u16, opcode: Opcode) {
+        let quirks = self.quirk_dependence(opcode);
+        let variant = self.variant;
+
+        if let Some(linter) = &mut self.linter {
+            for quirk in quirks {
+                linter.record(quirk, pc);
+            }
+
+            linter.track_index(pc, opcode, variant);
+        }
+    }
+
+    /// Quirks that change what the instruction does with the current registers and memory (8Fy6 and 8FyE can depend
+    /// on two)
+    fn quirk_dependence(&self, opcode: Opcode) -> Vec<Quirk> {
+        let (x, y) = (opcode.x(), opcode.y());
+        let v = &self.regfile.gprs;
+
+        // Bnnn is something else on CHIP-8X, and BBnn/BFnn are relative branches on CHIP-8E
+        let is_jp_idx = match self.variant {
+            Variant::Chip8X => false,
+            Variant::Chip8E => x != 0xB && x != 0xF,
+            _ => true,
+        };
+
+        let (kind, n) = (opcode.raw() >> 12, opcode.n());
+
+        let checks = [
+            (Quirk::Shift, kind == 0x8 && matches!(n, 0x6 | 0xE) && x != y && v[x] != v[y]),
+            (Quirk::VfOrder, kind == 0x8 && matches!(n, 0x4..=0x7 | 0xE) && x == VF),
+            (Quirk::Jump, kind == 0xB && is_jp_idx && x != 0 && v[x] != v[0]),
+            (Quirk::Clip, kind == 0xD && !self.megachip.is_enabled() && self.sprite_crosses_edge(opcode)),
+        ];
+
+        checks.iter().filter(|(_, depends)| *depends).map(|(quirk, _)| *quirk).collect()
+    }
+
+    /// Returns true if lit pixels of the sprite Dxyn is about to draw fall past the right or bottom edge
+    fn sprite_crosses_edge(&self, opcode: Opcode) -> bool {
+        let (width, height) = (self.display.width(), self.display.height());
+        let v = &self.regfile.gprs;
+
+        let (x, y) = (v[opcode.x()] as usize % width, v[opcode.y()] as usize % height);
+
+        (0..opcode.n()).any(|row| {
+            let pixels = self.bus.peek_byte(Address::new(self.regfile.index.wrapping_add(row as u32)));
+            let past_right = if width - x < 8 { pixels & (0xFF >> (width - x)) } else { 0 };
+
+            pixels != 0 && (y + row >= height || past_right != 0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Core;
+
+    #[test]
+    fn shift_into_vf() {
+        // VF = 5, V1 = 2, 8F16 (shifts V1 into VF, or VF in place), loop
+        let mut core = Core::new(&[0x6F, 0x05, 0x61, 0x02, 0x8F, 0x16, 0x12, 0x06], 0);
+
+        core.enable_quirk_linter();
+        core.frame();
+
+        let findings: Vec<_> = core.quirk_linter().unwrap().findings().collect();
+
+        assert_eq!(findings, [(Quirk::Shift, 0x204, 1), (Quirk::VfOrder, 0x204, 1)]);
+    }
+}
@@ -0,0 +1,527 @@
+use crate::{
+    bus::{Address, memory::Memory},
+    cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode, regfile::VF},
+    display::Display,
+};
+
+/// MegaChip instructions, matched before the base set (00E0 and DxyN fall back to it outside of MegaChip mode)
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 20] = [
+    OpcodeDesc(0x0010, 0xFFFF, Cpu::mega_off),
+    OpcodeDesc(0x0011, 0xFFFF, Cpu::mega_on),
+    OpcodeDesc(0x00B0, 0xFFF0, Cpu::scroll_up),
+    OpcodeDesc(0x00C0, 0xFFF0, Cpu::scroll_down),
+    OpcodeDesc(0x00E0, 0xFFFF, Cpu::cls_mega),
+    OpcodeDesc(0x00FB, 0xFFFF, Cpu::scroll_right),
+    OpcodeDesc(0x00FC, 0xFFFF, Cpu::scroll_left),
+    OpcodeDesc(0x00FD, 0xFFFF, Cpu::exit),
+    OpcodeDesc(0x00FE, 0xFFFF, Cpu::lores),
+    OpcodeDesc(0x00FF, 0xFFFF, Cpu::hires),
+    OpcodeDesc(0x0100, 0xFF00, Cpu::ldhi),
+    OpcodeDesc(0x0200, 0xFF00, Cpu::ldpal),
+    OpcodeDesc(0x0300, 0xFF00, Cpu::sprw),
+    OpcodeDesc(0x0400, 0xFF00, Cpu::sprh),
+    OpcodeDesc(0x0500, 0xFF00, Cpu::alpha),
+    OpcodeDesc(0x0600, 0xFFF0, Cpu::digisnd),
+    OpcodeDesc(0x0700, 0xFFFF, Cpu::stopsnd),
+    OpcodeDesc(0x0800, 0xFFF0, Cpu::bmode),
+    OpcodeDesc(0x0900, 0xFF00, Cpu::ccol),
+    OpcodeDesc(0xD000, 0xF000, Cpu::drw_mega),
+];
+
+/// How sprite pixels combine with the pixels under them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Blend {
+    #[default]
+    Normal,
+    Percent25,
+    Percent50,
+    Percent75,
+    Add,
+    Multiply,
+}
+
+impl Blend {
+    fn from_mode(mode: usize) -> Self {
+        match mode {
+            1 => Blend::Percent25,
+            2 => Blend::Percent50,
+            3 => Blend::Percent75,
+            4 => Blend::Add,
+            5 => Blend::Multiply,
+            _ => Blend::Normal,
+        }
+    }
+
+    fn channel(&self, src: u32, dst: u32) -> u32 {
+        match self {
+            Blend::Normal => src,
+            Blend::Percent25 => (src + 3 * dst) / 4,
+            Blend::Percent50 => (src + dst) / 2,
+            Blend::Percent75 => (3 * src + dst) / 4,
+            Blend::Add => u32::min(src + dst, 0xFF),
+            Blend::Multiply => src * dst / 0xFF,
+        }
+    }
+
+    /// Blends an ARGB sprite color over a 0RGB pixel, weighting the result by the sprite alpha
+    fn apply(&self, src: u32, dst: u32) -> u32 {
+        let alpha = src >> 24;
+
+        (0..3).map(|i| 8 * i).fold(0, |out, shift| {
+            let (s, d) = ((src >> shift) & 0xFF, (dst >> shift) & 0xFF);
+            let blended = self.channel(s, d);
+
+            out | ((blended * alpha + d * (0xFF - alpha)) / 0xFF) << shift
+        })
+    }
+}
+
+/// 8-bit unsigned samples played from memory
+///
+/// The data at the start address is a 6-byte header (16-bit sample rate, 24-bit length, a reserved byte) followed by
+/// the samples.
+struct DigitalSound {
+    start: u32,
+    len: u64,
+    rate: u64,
+    looping: bool,
+
+    /// Frames played before the current one
+    elapsed: u64,
+}
+
+impl DigitalSound {
+    const HEADER_SIZE: u32 = 6;
+
+    fn new(mem: &Memory, addr: u32, looping: bool) -> Self {
+        let byte = |i: u32| mem[Address::new(addr.wrapping_add(i)).masked_address(mem)] as u64;
+
+        Self {
+            start: addr.wrapping_add(Self::HEADER_SIZE),
+            len: byte(2) << 16 | byte(3) << 8 | byte(4),
+            rate: byte(0) << 8 | byte(1),
+            looping,
+            elapsed: 0,
+        }
+    }
+
+    /// Moves on to the next 60 Hz frame, returning false once a one-shot sound has ended
+    fn advance(&mut self) -> bool {
+        self.elapsed += 1;
+
+        self.looping || self.elapsed * self.rate / 60 < self.len
+    }
+
+    /// Resamples the current frame's samples to `sample_rate`, mixing them into `out`
+    fn mix(&self, mem: &Memory, out: &mut [i16], sample_rate: u32) {
+        let sample_rate = sample_rate as u64;
+
+        if self.len == 0 || sample_rate == 0 {
+            return;
+        }
+
+        for (i, out) in out.iter_mut().enumerate() {
+            let mut position = (self.elapsed * sample_rate + 60 * i as u64) * self.rate / (60 * sample_rate);
+
+            if self.looping {
+                position %= self.len;
+            } else if position >= self.len {
+                break;
+            }
+
+            let addr = Address::new(self.start.wrapping_add(position as u32)).masked_address(mem);
+            let sample = (mem[addr] as i16 - 0x80) * 0x40;
+
+            *out = out.saturating_add(sample);
+        }
+    }
+}
+
+/// MegaChip state: mode, palette, sprite size, blending and sound
+pub struct MegaChip {
+    enabled: bool,
+
+    /// ARGB colors; index 0 is transparent when drawing
+    palette: [u32; 256],
+
+    /// Sprite size in pixels (bytes); 0 means 256
+    sprite_width: u8,
+    sprite_height: u8,
+
+    /// Opacity of the whole screen
+    alpha: u8,
+
+    blend: Blend,
+    collision_color: u8,
+    sound: Option<DigitalSound>,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            palette: [0; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            alpha: 0xFF,
+            blend: Blend::Normal,
+            collision_color: 0,
+            sound: None,
+        }
+    }
+}
+
+impl MegaChip {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound.is_some()
+    }
+
+    /// Advances digitised sound playback by one frame
+    pub(super) fn tick(&mut self) {
+        if self.sound.as_mut().is_some_and(|sound| !sound.advance()) {
+            self.sound = None;
+        }
+    }
+
+    /// Mixes the digitised sound of the current frame into `out`, resampled to `sample_rate`
+    pub fn mix_sound(&self, mem: &Memory, out: &mut [i16], sample_rate: u32) {
+        if let Some(sound) = &self.sound {
+            sound.mix(mem, out, sample_rate);
+        }
+    }
+}
+
+impl Cpu {
+    /// Alpha of the whole screen
+    fn alpha(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.alpha = opcode.kk();
+
+        None
+    }
+
+    /// Set sprite blend mode
+    fn bmode(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.blend = Blend::from_mode(opcode.n());
+
+        None
+    }
+
+    /// Collision color = kk
+    fn ccol(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.collision_color = opcode.kk();
+
+        None
+    }
+
+    /// Present the frame drawn so far and clear the screen (plain clear outside of MegaChip mode)
+    fn cls_mega(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        if !self.megachip.enabled {
+            return self.cls(opcode);
+        }
+
+        self.display.present();
+
+        let (pixels, indices) = self.display.layers_mut();
+
+        pixels.fill(0);
+        indices.fill(0);
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Play the digitised sound at I, looping with n == 0
+    fn digisnd(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+
+        self.megachip.sound = Some(DigitalSound::new(self.bus.memory(), index, opcode.n() == 0));
+
+        None
+    }
+
+    /// Draw a sprite of palette indices (sprite width x height bytes at I) with the current blend mode
+    ///
+    /// VF is set when a visible pixel lands on a pixel of the collision color (never for color 0). Outside of MegaChip
+    /// mode, draws a regular sprite, or a 16x16 one for Dxy0.
+    fn drw_mega(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        if !self.megachip.enabled {
+            return if opcode.n() == 0 { self.drw_big(opcode) } else { self.drw(opcode) };
+        }
+
+        let (index, x, y) = (*self.i(), *self.v(opcode.x()) as usize, *self.v(opcode.y()) as usize);
+
+        let size = |n: u8| if n == 0 { 256 } else { n as usize };
+        let (sprite_width, sprite_height) = (size(self.megachip.sprite_width), size(self.megachip.sprite_height));
+
+        let (width, height) = (self.display.width(), self.display.height());
+        let mut has_collided = false;
+
+        for row in 0..sprite_height {
+            for col in 0..sprite_width {
+                let color_index = self.bus.read_byte(Address::new(index.wrapping_add((row * sprite_width + col) as u32)));
+                let (xc, yr) = (x + col, y + row);
+
+                // Transparent, or clipped at the screen edges
+                if color_index == 0 || xc >= width || yr >= height {
+                    continue;
+                }
+
+                let (pixels, indices) = self.display.layers_mut();
+                let display_idx = width * yr + xc;
+
+                has_collided |= self.megachip.collision_color != 0 && indices[display_idx] == self.megachip.collision_color;
+
+                pixels[display_idx] = self.megachip.blend.apply(self.megachip.palette[color_index as usize], pixels[display_idx]);
+                indices[display_idx] = color_index;
+            }
+        }
+
+        *self.v(VF) = has_collided as u8;
+
+        None
+    }
+
+    /// Draw the 16x16 sprite at I (two bytes per row) like a regular sprite
+    fn drw_big(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let (width, height) = (self.display.width(), self.display.height());
+        let (index, x, y) = (*self.i(), *self.v(opcode.x()) as usize % width, *self.v(opcode.y()) as usize % height);
+        let mut has_collided = false;
+
+        for row in 0..16 {
+            let mut byte = |col: u32| self.bus.read_byte(Address::new(index.wrapping_add(2 * row + col)));
+            let pixels = u16::from_be_bytes([byte(0), byte(1)]);
+            let yr = y + row as usize;
+
+            // Clipped at the bottom and right edges, or wrapped around without the clip quirk
+            if yr >= height && self.quirks.clip {
+                break;
+            }
+
+            for col in 0..16 {
+                let xc = x + col;
+
+                if xc >= width && self.quirks.clip {
+                    break;
+                }
+
+                if pixels & (0x8000 >> col) != 0 {
+                    let display_idx = width * (yr % height) + xc % width;
+
+                    has_collided |= self.display[display_idx] != 0;
+                    self.display[display_idx] ^= Display::COLOR_WHITE;
+                }
+            }
+        }
+
+        *self.v(VF) = has_collided as u8;
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Stop the program (SUPER-CHIP exits to the HP 48): loop on this instruction
+    fn exit(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.regfile.rewind_pc();
+
+        None
+    }
+
+    /// SUPER-CHIP high resolution (128x64), outside of MegaChip mode
+    fn hires(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        if self.megachip.enabled {
+            return None;
+        }
+
+        self.display = Display::new(Display::SCHIP_WIDTH, Display::SCHIP_HEIGHT);
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Back to low resolution (64x32), outside of MegaChip mode
+    fn lores(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        if self.megachip.enabled {
+            return None;
+        }
+
+        self.display = Display::new(Display::WIDTH, Display::HEIGHT);
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// I = nnnnnn, a 24-bit address whose low 16 bits are the next word
+    fn ldhi(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let pc = *self.pc();
+        let low = self.bus.fetch_operand(Address::new(pc));
+
+        self.regfile.advance_pc();
+
+        *self.i() = (opcode.kk() as u32) << 16 | low as u32;
+
+        None
+    }
+
+    /// Load kk ARGB palette colors from I, starting at index 1
+    fn ldpal(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+
+        for color in 0..opcode.kk() as u32 {
+            let bytes = [0, 1, 2, 3].map(|i| self.bus.read_byte(Address::new(index.wrapping_add(4 * color + i))));
+
+            self.megachip.palette[color as usize + 1] = u32::from_be_bytes(bytes);
+        }
+
+        None
+    }
+
+    /// Leave MegaChip mode (back to 64x32)
+    fn mega_off(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.enabled = false;
+        self.display = Display::new(Display::WIDTH, Display::HEIGHT);
+
+        None
+    }
+
+    /// Enter MegaChip mode (256x192)
+    fn mega_on(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.enabled = true;
+        self.display = Display::new(Display::MEGA_WIDTH, Display::MEGA_HEIGHT);
+
+        None
+    }
+
+    /// Scroll the screen down n lines
+    fn scroll_down(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let lines = self.display.width() * opcode.n();
+        let (pixels, indices) = self.display.layers_mut();
+        let len = pixels.len();
+
+        pixels.copy_within(..len - lines, lines);
+        indices.copy_within(..len - lines, lines);
+        pixels[..lines].fill(0);
+        indices[..lines].fill(0);
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Scroll the screen 4 pixels left
+    fn scroll_left(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        let width = self.display.width();
+        let (pixels, indices) = self.display.layers_mut();
+
+        for (pixels, indices) in pixels.chunks_mut(width).zip(indices.chunks_mut(width)) {
+            pixels.copy_within(4.., 0);
+            indices.copy_within(4.., 0);
+            pixels[width - 4..].fill(0);
+            indices[width - 4..].fill(0);
+        }
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Scroll the screen 4 pixels right
+    fn scroll_right(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        let width = self.display.width();
+        let (pixels, indices) = self.display.layers_mut();
+
+        for (pixels, indices) in pixels.chunks_mut(width).zip(indices.chunks_mut(width)) {
+            pixels.copy_within(..width - 4, 4);
+            indices.copy_within(..width - 4, 4);
+            pixels[..4].fill(0);
+            indices[..4].fill(0);
+        }
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Scroll the screen up n lines
+    fn scroll_up(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let width = self.display.width();
+        let lines = width * opcode.n();
+        let (pixels, indices) = self.display.layers_mut();
+
+        pixels.copy_within(lines.., 0);
+        indices.copy_within(lines.., 0);
+
+        let len = pixels.len();
+
+        pixels[len - lines..].fill(0);
+        indices[len - lines..].fill(0);
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Sprite height = kk
+    fn sprh(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.sprite_height = opcode.kk();
+
+        None
+    }
+
+    /// Sprite width = kk
+    fn sprw(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.sprite_width = opcode.kk();
+
+        None
+    }
+
+    /// Stop the digitised sound
+    fn stopsnd(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.megachip.sound = None;
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Core, config::{Config, Variant}, display::Display};
+
+    use alloc::vec::Vec;
+
+    #[test]
+    fn schip_instructions() {
+        let mut config = Config::default();
+
+        config.set_variant(Variant::MegaChip);
+
+        let program = [
+            0x00, 0xFF, // HIGH
+            0x60, 0x07, // LD V0, 07
+            0xF0, 0x30, // LD HF, V0
+            0xD1, 0x10, // DRW V1, V1, 0
+            0x00, 0xC2, // SCD 2
+            0x00, 0xFB, // SCR
+            0x00, 0xFC, // SCL
+            0xF0, 0x75, // LD R, V0
+            0x60, 0x00, // LD V0, 00
+            0xF0, 0x85, // LD V0, R
+            0x00, 0xFD, // EXIT
+        ];
+
+        let mut core = Core::with_config(&program, 0, config);
+
+        // One frame per drawing instruction
+        for _ in 0..6 {
+            core.frame();
+        }
+
+        assert_eq!(core.display().width(), Display::SCHIP_WIDTH);
+        assert_eq!(core.regfile().pc, 0x214);
+        assert_eq!(core.regfile().gprs[0], 0x07);
+        assert_eq!(core.user_flags()[0], 0x07);
+
+        // Dxy0 drew the big 7 (FF FF 03 06 ...) two bytes per row from (0, 0), then everything moved down 2 lines
+        let display = core.display();
+        let row = |y: usize| (0..16).map(|x| (display[Display::SCHIP_WIDTH * y + x] != 0) as u8).collect::<Vec<_>>();
+
+        assert!(row(0).iter().chain(&row(1)).all(|&lit| lit == 0));
+        assert_eq!(row(2), [1; 16]);
+        assert_eq!(row(3), [0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0]);
+    }
+}
@@ -0,0 +1,869 @@
+use crate::{
+    bus::{Address, Bus, BusFault},
+    config::{Quirks, RplFlags, Variant},
+    cpu::{chip8x::IoPort, ext::{Context, CustomOpcode}, lint::QuirkLinter, megachip::MegaChip, opcode::Opcode, profile::Profiler, regfile::{RegFile, VF}, rng::Rng},
+    display::Display,
+    keypad::Keypad,
+};
+
+use alloc::vec::Vec;
+
+pub mod chip8e;
+pub mod chip8i;
+pub mod chip8x;
+pub mod ext;
+pub mod lint;
+pub mod megachip;
+pub mod opcode;
+pub mod profile;
+pub mod regfile;
+mod rng;
+pub mod rpl;
+pub mod sys;
+pub mod vipmap;
+
+pub enum CpuEvent {
+    Draw,
+    WaitForKey,
+
+    /// FxFB is waiting for a byte on the expansion port
+    WaitForInput,
+
+    /// The instruction wrote to a device or read-only region that rejected the write, tripped strict mode, or called
+    /// machine code that never returned
+    Fault(BusFault),
+}
+
+type OpcodePattern = u16;
+type OpcodeMask = u16;
+type OpcodeHandler = fn(&mut Cpu, Opcode) -> Option<CpuEvent>;
+
+/// Opcode descriptor (opcode pattern, mask, handler)
+#[derive(Clone, Copy)]
+struct OpcodeDesc(OpcodePattern, OpcodeMask, OpcodeHandler);
+
+#[derive(Default)]
+struct OpcodeMatcher {
+    registered_opcodes: Vec<OpcodeDesc>,
+}
+
+impl OpcodeMatcher {
+    pub fn register(&mut self, desc: OpcodeDesc) {
+        self.registered_opcodes.push(desc);
+    }
+
+    /// Matches opcode against registered opcodes and returns corresponding opcode handler, if any
+    pub fn find_opcode(&self, opcode: u16) -> Option<OpcodeHandler> {
+        for desc in self.registered_opcodes.iter() {
+            let OpcodeDesc(pattern, mask, handler) = *desc;
+
+            let masked_opcode = opcode & mask;
+
+            if masked_opcode == pattern {
+                return Some(handler);
+            }
+        }
+
+        None
+    }
+
+    /// Like `find_opcode`, falling back to a handler that panics
+    pub fn match_opcode(&self, opcode: u16) -> OpcodeHandler {
+        self.find_opcode(opcode).unwrap_or(Cpu::dummy)
+    }
+}
+
+#[derive(Default)]
+pub struct Stack {
+    stack: Vec<u16>,
+
+    /// Stack pointer with the VIP memory map, where return addresses live in memory between `VIP_STACK` and
+    /// `VIP_STACK_END` (high byte first)
+    sp: Option<u16>,
+}
+
+impl Stack {
+    const MAX_DEPTH: usize = 16;
+
+    /// Creates an empty stack, kept in memory with the VIP memory map
+    pub fn new(vip_memory_map: bool) -> Self {
+        Self { stack: Vec::new(), sp: if vip_memory_map { Some(vipmap::VIP_STACK_END) } else { None } }
+    }
+
+    /// Number of return addresses on the stack
+    pub fn depth(&self) -> usize {
+        match self.sp {
+            Some(sp) => ((vipmap::VIP_STACK_END - sp) / 2) as usize,
+            None => self.stack.len(),
+        }
+    }
+
+    /// Whether another push would overflow the stack
+    pub fn is_full(&self) -> bool {
+        match self.sp {
+            Some(sp) => sp < vipmap::VIP_STACK + 2,
+            None => self.stack.len() >= Self::MAX_DEPTH,
+        }
+    }
+
+    pub fn pop(&mut self, bus: &mut Bus) -> u16 {
+        match &mut self.sp {
+            Some(sp) => {
+                assert!(*sp < vipmap::VIP_STACK_END);
+
+                let data = u16::from_be_bytes([bus.read_byte(Address::new(*sp)), bus.read_byte(Address::new(*sp + 1))]);
+
+                *sp += 2;
+
+                data
+            }
+            None => {
+                assert!(!self.stack.is_empty());
+
+                self.stack.pop().unwrap()
+            }
+        }
+    }
+
+    pub fn push(&mut self, bus: &mut Bus, data: u16) {
+        match &mut self.sp {
+            Some(sp) => {
+                assert!(*sp >= vipmap::VIP_STACK + 2);
+
+                *sp -= 2;
+
+                let [high, low] = data.to_be_bytes();
+
+                bus.write_byte(Address::new(*sp), high);
+                bus.write_byte(Address::new(*sp + 1), low);
+            }
+            None => {
+                assert!(self.stack.len() < Self::MAX_DEPTH);
+
+                self.stack.push(data);
+            }
+        }
+    }
+}
+
+pub struct Cpu {
+    bus: Bus,
+    display: Display,
+    keypad: Keypad,
+
+    /// CHIP-8X keypad read by ExF2/ExF5
+    second_keypad: Keypad,
+    port: IoPort,
+    megachip: MegaChip,
+    matcher: OpcodeMatcher,
+
+    /// Instructions registered by the embedder, matched before all others (later ones first)
+    custom_opcodes: Vec<CustomOpcode>,
+    regfile: RegFile,
+    stack: Stack,
+    rng: Rng,
+    quirks: Quirks,
+    variant: Variant,
+
+    /// Stack and display live in memory (`vipmap`)
+    vip_memory_map: bool,
+
+    /// Where the small font starts, for Fx29
+    font_address: u16,
+    rpl_flags: RplFlags,
+
+    /// RPL user flags written by Fx75; like on the HP 48, they survive a reset
+    user_flags: [u8; 16],
+
+    /// CHIP-8E Fx4F has set the delay timer and is waiting for it to run out
+    delay_wait: bool,
+    profiler: Option<Profiler>,
+    linter: Option<QuirkLinter>,
+}
+
+impl Cpu {
+    pub fn new(bus: Bus, seed: u64, quirks: Quirks, variant: Variant, vip_memory_map: bool, font_address: u16, rpl_flags: RplFlags) -> Self {
+        // Populate matcher with descriptors
+        const OPCODE_DESCS: [OpcodeDesc; 36] = [
+            OpcodeDesc(0x00E0, 0xFFFF, Cpu::cls),
+            OpcodeDesc(0x00EE, 0xFFFF, Cpu::ret),
+            OpcodeDesc(0x0000, 0xF000, Cpu::sys),
+            OpcodeDesc(0x1000, 0xF000, Cpu::jp),
+            OpcodeDesc(0x2000, 0xF000, Cpu::call),
+            OpcodeDesc(0x3000, 0xF000, Cpu::se_imm),
+            OpcodeDesc(0x4000, 0xF000, Cpu::sne_imm),
+            OpcodeDesc(0x5000, 0xF00F, Cpu::se_reg),
+            OpcodeDesc(0x6000, 0xF000, Cpu::ldv_imm),
+            OpcodeDesc(0x7000, 0xF000, Cpu::add_imm),
+            OpcodeDesc(0x8000, 0xF00F, Cpu::ldv_reg),
+            OpcodeDesc(0x8001, 0xF00F, Cpu::or),
+            OpcodeDesc(0x8002, 0xF00F, Cpu::and),
+            OpcodeDesc(0x8003, 0xF00F, Cpu::xor),
+            OpcodeDesc(0x8004, 0xF00F, Cpu::add_reg),
+            OpcodeDesc(0x8005, 0xF00F, Cpu::sub),
+            OpcodeDesc(0x8006, 0xF00F, Cpu::shr),
+            OpcodeDesc(0x8007, 0xF00F, Cpu::subn),
+            OpcodeDesc(0x800E, 0xF00F, Cpu::shl),
+            OpcodeDesc(0x9000, 0xF00F, Cpu::sne_reg),
+            OpcodeDesc(0xA000, 0xF000, Cpu::ldi_imm),
+            OpcodeDesc(0xB000, 0xF000, Cpu::jp_idx),
+            OpcodeDesc(0xD000, 0xF000, Cpu::drw),
+            OpcodeDesc(0xC000, 0xF000, Cpu::rnd),
+            OpcodeDesc(0xE09E, 0xF0FF, Cpu::skp),
+            OpcodeDesc(0xE0A1, 0xF0FF, Cpu::sknp),
+            OpcodeDesc(0xF007, 0xF0FF, Cpu::ldv_dt),
+            OpcodeDesc(0xF00A, 0xF0FF, Cpu::ldv_key),
+            OpcodeDesc(0xF015, 0xF0FF, Cpu::lddt),
+            OpcodeDesc(0xF018, 0xF0FF, Cpu::ldst),
+            OpcodeDesc(0xF01E, 0xF0FF, Cpu::addi),
+            OpcodeDesc(0xF029, 0xF0FF, Cpu::ldf),
+            OpcodeDesc(0xF030, 0xF0FF, Cpu::ldf_big),
+            OpcodeDesc(0xF033, 0xF0FF, Cpu::ldb),
+            OpcodeDesc(0xF055, 0xF0FF, Cpu::ldi_mem),
+            OpcodeDesc(0xF065, 0xF0FF, Cpu::ldv_mem),
+        ];
+
+        let mut matcher = OpcodeMatcher::default();
+
+        if vip_memory_map {
+            for desc in vipmap::OPCODE_DESCS {
+                matcher.register(desc);
+            }
+        }
+
+        if rpl_flags != RplFlags::None {
+            for desc in rpl::OPCODE_DESCS {
+                matcher.register(desc);
+            }
+        }
+
+        match variant {
+            Variant::Chip8E => {
+                for desc in chip8e::OPCODE_DESCS {
+                    matcher.register(desc);
+                }
+            }
+            Variant::Chip8I => {
+                for desc in chip8i::OPCODE_DESCS {
+                    matcher.register(desc);
+                }
+            }
+            Variant::Chip8X => {
+                for desc in chip8x::OPCODE_DESCS {
+                    matcher.register(desc);
+                }
+            }
+            // Clears the 64x64 screen
+            Variant::Hires => matcher.register(OpcodeDesc(0x0230, 0xFFFF, Cpu::cls)),
+            Variant::MegaChip => {
+                for desc in megachip::OPCODE_DESCS.iter().chain(&rpl::OPCODE_DESCS) {
+                    matcher.register(*desc);
+                }
+            }
+            Variant::Chip8 => {}
+        }
+
+        let (width, height) = variant.display_size();
+
+        for desc in OPCODE_DESCS {
+            matcher.register(desc);
+        }
+
+        Self {
+            bus,
+            display: Display::new(width, height),
+            keypad: Keypad::default(),
+            second_keypad: Keypad::default(),
+            port: IoPort::default(),
+            megachip: MegaChip::default(),
+            matcher,
+            custom_opcodes: Vec::new(),
+            regfile: RegFile::default(),
+            stack: Stack::new(vip_memory_map),
+            rng: Rng::new(seed),
+            quirks,
+            variant,
+            vip_memory_map,
+            font_address,
+            rpl_flags,
+            user_flags: [0; 16],
+            delay_wait: false,
+            profiler: None,
+            linter: None,
+        }
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// Adds an instruction, or replaces the matching cases of an existing one
+    pub fn register_opcode(&mut self, opcode: CustomOpcode) {
+        self.custom_opcodes.push(opcode);
+    }
+
+    /// Starts counting executions per address, instruction and call stack
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new(self.variant));
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Starts watching for instructions whose result depends on a quirk
+    pub fn enable_quirk_linter(&mut self) {
+        self.linter = Some(QuirkLinter::default());
+    }
+
+    pub fn quirk_linter(&self) -> Option<&QuirkLinter> {
+        self.linter.as_ref()
+    }
+
+    /// RPL user flags the program can reach (none unless `Config::rpl_flags` enables Fx75/Fx85)
+    pub fn user_flags(&self) -> &[u8] {
+        &self.user_flags[..self.rpl_flags.count()]
+    }
+
+    pub fn user_flags_mut(&mut self) -> &mut [u8] {
+        &mut self.user_flags[..self.rpl_flags.count()]
+    }
+
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.keypad
+    }
+
+    pub fn second_keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.second_keypad
+    }
+
+    pub fn port(&self) -> &IoPort {
+        &self.port
+    }
+
+    pub fn port_mut(&mut self) -> &mut IoPort {
+        &mut self.port
+    }
+
+    pub fn megachip(&self) -> &MegaChip {
+        &self.megachip
+    }
+
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    pub fn regfile(&self) -> &RegFile {
+        &self.regfile
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// Returns true while the sound timer is non-zero (the beeper is on)
+    pub fn is_sound_active(&self) -> bool {
+        self.regfile.sound_timer.is_running()
+    }
+
+    /// Resets the registers, stack, display and MegaChip state and sets PC to `entry_point` (memory, keypad and RNG are
+    /// left untouched)
+    pub fn reset(&mut self, entry_point: u16) {
+        let (width, height) = self.variant.display_size();
+
+        self.regfile = RegFile { pc: entry_point, ..RegFile::default() };
+        self.stack = Stack::new(self.vip_memory_map);
+        self.display = Display::new(width, height);
+        self.megachip = MegaChip::default();
+        self.delay_wait = false;
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.unwind();
+        }
+
+        self.sync_display();
+    }
+
+    /// Picks up changes made to the framebuffer in memory with the VIP memory map
+    pub fn sync_display(&mut self) {
+        if self.vip_memory_map {
+            self.refresh_display();
+        }
+    }
+
+    /// Executes a single Chip-8 instruction
+    pub fn step(&mut self) -> Option<CpuEvent> {
+        let pc = *self.pc();
+
+        let opcode = Opcode::new(self.bus.fetch_opcode(Address::new(pc)));
+        let depth = self.stack.depth();
+
+        if self.linter.is_some() {
+            self.lint(pc, opcode);
+        }
+
+        self.regfile.advance_pc();
+    
+        let custom = self.custom_opcodes.iter().rev().find(|custom| opcode.raw() & custom.mask == custom.pattern);
+
+        let event = match custom.map(|custom| custom.handler) {
+            Some(handler) => handler(&mut Context::new(self), opcode),
+            None => self.matcher.match_opcode(opcode.raw())(self, opcode),
+        };
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(pc, opcode.raw(), self.regfile.pc, depth, self.stack.depth());
+        }
+
+        match self.bus.take_fault() {
+            Some(fault) => Some(CpuEvent::Fault(fault)),
+            None => event,
+        }
+    }
+
+    /// Ticks the delay and sound timers and advances digitised sound
+    pub fn tick(&mut self) {
+        self.regfile.delay_timer.decrement();
+        self.regfile.sound_timer.decrement();
+        self.megachip.tick();
+    }
+
+    /// Skip instruction if condition is true
+    fn skip(&mut self, condition: bool) {
+        if condition {
+            self.regfile.advance_pc()
+        }
+    }
+
+    /// Returns a mutable reference to the delay timer
+    fn dt(&mut self) -> &mut u8 {
+        self.regfile.delay_timer.counter()
+    }
+
+    /// Returns a mutable reference to the sound timer
+    fn st(&mut self) -> &mut u8 {
+        self.regfile.sound_timer.counter()
+    }
+
+    /// Returns a mutable reference to PC
+    fn pc(&mut self) -> &mut u16 {
+        &mut self.regfile.pc
+    }
+
+    /// Returns a mutable reference to the index register
+    fn i(&mut self) -> &mut u32 {
+        &mut self.regfile.index
+    }
+
+    /// Returns a mutable reference to a GPR
+    fn v(&mut self, idx: usize) -> &mut u8 {
+        &mut self.regfile.gprs[idx]
+    }
+
+    /// Vx = result, VF = flag, in the order selected by the VF order quirk
+    fn set_with_flag(&mut self, x: usize, result: u8, flag: bool) {
+        if self.quirks.vf_order {
+            (*self.v(VF), *self.v(x)) = (flag as u8, result);
+        } else {
+            (*self.v(x), *self.v(VF)) = (result, flag as u8);
+        }
+    }
+
+    /// VF = 0 for AND, OR and XOR with the logic quirk
+    fn reset_vf(&mut self) {
+        if self.quirks.logic {
+            *self.v(VF) = 0;
+        }
+    }
+
+    /// Moves I past the registers loaded or stored, unless the load/store quirk is set
+    fn advance_i(&mut self, x: usize) {
+        if !self.quirks.load_store {
+            *self.i() = self.i().wrapping_add(x as u32).wrapping_add(1);
+        }
+    }
+
+    // --- Opcode handlers
+
+    fn dummy(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        panic!("No handler for opcode {:04X}", opcode.raw());
+    }
+
+    /// Vx += kk
+    fn add_imm(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let x = opcode.x();
+
+        *self.v(x) = self.v(x).wrapping_add(opcode.kk());
+
+        None
+    }
+    
+    /// Vx += Vy, VF = carry
+    fn add_reg(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let x = opcode.x();
+
+        let (result, has_overflowed) = self.v(x).overflowing_add(*self.v(opcode.y()));
+
+        self.set_with_flag(x, result, has_overflowed);
+
+        None
+    }
+
+    /// I += Vx
+    fn addi(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.i() = self.i().wrapping_add(*self.v(opcode.x()) as u32);
+
+        None
+    }
+    
+    /// Vx = Vx AND Vy
+    fn and(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) &= *self.v(opcode.y());
+
+        self.reset_vf();
+
+        None
+    }
+
+    /// Call subroutine
+    fn call(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let return_addr = *self.pc();
+
+        self.stack.push(&mut self.bus, return_addr);
+
+        *self.pc() = opcode.nnn();
+
+        None
+    }
+
+    /// Clear screen
+    fn cls(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        self.display.as_mut_slice().fill(0);
+
+        None
+    }
+
+    /// Draw sprite
+    fn drw(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let (width, height) = (self.display.width(), self.display.height());
+
+        let (index, x, y) = (
+            *self.i(),
+            *self.v(opcode.x()) as usize % width,
+            *self.v(opcode.y()) as usize % height,
+        );
+
+        let mut has_collided = false;
+        let clip = self.quirks.clip;
+
+        'drw_loop: {
+            let display = &mut self.display;
+
+            for n in 0..opcode.n() {
+                // Get next row of pixels
+                let pixels = self.bus.read_byte(Address::new(index.wrapping_add(n as u32))).reverse_bits();
+
+                let yn = y + n;
+
+                // Y past the bottom causes clipping (or wraps around without the clip quirk)
+                if yn >= height && clip {
+                    break 'drw_loop;
+                }
+    
+                // Draw every individual pixel as either white or black
+                for i in 0..8 {
+                    let xi = x + i as usize;
+
+                    // X past the right edge causes clipping (or wraps around without the clip quirk)
+                    if xi >= width && clip {
+                        break;
+                    }
+
+                    let display_idx = width * (yn % height) + (xi % width);
+
+                    // 1 == white
+                    let (pixel, old_pixel) = (
+                        Display::COLOR_WHITE * (pixels.wrapping_shr(i) & 1) as u32,
+                        display[display_idx],
+                    );
+    
+                    has_collided |= (pixel & old_pixel) == Display::COLOR_WHITE;
+    
+                    display[display_idx] ^= pixel;
+                }
+            }
+        }
+
+        *self.v(VF) = has_collided as u8;
+
+        Some(CpuEvent::Draw)
+    }
+
+    /// Jump
+    fn jp(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.pc() = opcode.nnn();
+
+        None
+    }
+
+    /// Jump with index (V0, or Vx with the jump quirk)
+    fn jp_idx(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let reg = if self.quirks.jump { opcode.x() } else { 0 };
+
+        *self.pc() = opcode.nnn().wrapping_add(*self.v(reg) as u16);
+
+        None
+    }
+
+    /// [I] = BCD(Vx)
+    fn ldb(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let (index, vx) = (*self.i(), *self.v(opcode.x()));
+
+        let digits = [vx / 100, (vx / 10) % 10, vx % 10];
+
+        for (i, digit) in digits.iter().enumerate() {
+            self.bus.write_byte(Address::new(index.wrapping_add(i as u32)), *digit);
+        }
+
+        None
+    }
+
+    /// Delay timer = Vx
+    fn lddt(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.dt() = *self.v(opcode.x());
+
+        None
+    }
+
+    /// I = address of font character x
+    fn ldf(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        use crate::font::Font;
+
+        *self.i() = (self.font_address as usize + Font::SMALL_GLYPH_SIZE * (*self.v(opcode.x()) as usize % Font::NUM_GLYPHS)) as u32;
+
+        None
+    }
+
+    /// I = address of the large (SUPER-CHIP) font character x, stored after the small font
+    fn ldf_big(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        use crate::font::Font;
+
+        *self.i() = (self.font_address as usize + Font::SMALL_SIZE + Font::BIG_GLYPH_SIZE * (*self.v(opcode.x()) as usize % Font::NUM_GLYPHS)) as u32;
+
+        None
+    }
+
+    /// I = nnn
+    fn ldi_imm(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.i() = opcode.nnn() as u32;
+
+        None
+    }
+
+    /// [I] = V0-Vx
+    fn ldi_mem(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+
+        for i in 0..=opcode.x() {
+            let vx = *self.v(i);
+    
+            self.bus.write_byte(Address::new(index.wrapping_add(i as u32)), vx);
+        }
+
+        self.advance_i(opcode.x());
+
+        None
+    }
+
+    /// Sound timer = Vx
+    fn ldst(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.st() = *self.v(opcode.x());
+
+        None
+    }
+
+    /// Vx = delay timer
+    fn ldv_dt(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) = *self.dt();
+
+        None
+    }
+    
+    /// Vx = kk
+    fn ldv_imm(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) = opcode.kk();
+
+        None
+    }
+
+    /// Vx = key
+    fn ldv_key(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let key = self.keypad.any_key();
+
+        if let Some(cpu_index) = key {
+            *self.v(opcode.x()) = cpu_index;
+
+            None
+        } else {
+            self.regfile.rewind_pc();
+
+            Some(CpuEvent::WaitForKey)
+        }        
+    }
+
+    /// V0-Vx = [I]
+    fn ldv_mem(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let index = *self.i();
+    
+        for i in 0..=opcode.x() {
+            *self.v(i) = self.bus.read_byte(Address::new(index.wrapping_add(i as u32)));
+        }
+
+        self.advance_i(opcode.x());
+
+        None
+    }
+    
+    /// Vx = Vy
+    fn ldv_reg(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+       *self.v(opcode.x()) = *self.v(opcode.y());
+
+       None
+    }
+    
+    /// Vx = Vx OR Vy
+    fn or(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) |= *self.v(opcode.y());
+
+        self.reset_vf();
+
+        None
+    }
+
+    /// Return
+    fn ret(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        *self.pc() = self.stack.pop(&mut self.bus);
+
+        None
+    }
+
+    /// Vx = random number AND kk
+    fn rnd(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) = self.rng.next_u8() & opcode.kk();
+
+        None
+    }
+
+    /// Skip if Vx == kk
+    fn se_imm(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let condition = *self.v(opcode.x()) == opcode.kk();
+
+        self.skip(condition);
+
+        None
+    }
+
+    /// Skip if Vx == Vy
+    fn se_reg(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let condition = *self.v(opcode.x()) == *self.v(opcode.y());
+
+        self.skip(condition);
+
+        None
+    }
+    
+    /// Vx = Vy << 1 (or Vx <<= 1 with the shift quirk), VF = carry
+    fn shl(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let vy = *self.v(if self.quirks.shift { opcode.x() } else { opcode.y() });
+
+        let (result, has_overflowed) = (vy.unbounded_shl(1), vy.reverse_bits() & 1 != 0);
+
+        self.set_with_flag(opcode.x(), result, has_overflowed);
+
+        None
+    }
+    
+    /// Vx = Vy >> 1 (or Vx >>= 1 with the shift quirk), VF = carry
+    fn shr(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let vy = *self.v(if self.quirks.shift { opcode.x() } else { opcode.y() });
+
+        let (result, has_overflowed) = (vy.unbounded_shr(1), vy & 1 != 0);
+
+        self.set_with_flag(opcode.x(), result, has_overflowed);
+
+        None
+    }
+
+    /// Skip if key x is pressed
+    fn skp(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let key = *self.v(opcode.x()) as usize;
+        let is_key_pressed = self.keypad.is_key_pressed(key);
+
+        self.skip(is_key_pressed);
+
+        None
+    }
+
+    /// Skip if key x is not pressed
+    fn sknp(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let key = *self.v(opcode.x()) as usize;
+        let is_key_pressed = self.keypad.is_key_pressed(key);
+
+        self.skip(!is_key_pressed);
+
+        None
+    }
+
+    /// Skip if Vx != kk
+    fn sne_imm(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let condition = *self.v(opcode.x()) != opcode.kk();
+
+        self.skip(condition);
+
+        None
+    }
+
+    /// Skip if Vx != Vy
+    fn sne_reg(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let condition = *self.v(opcode.x()) != *self.v(opcode.y());
+
+        self.skip(condition);
+
+        None
+    }
+    
+    /// Vx -= Vy, VF = !borrow
+    fn sub(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let x = opcode.x();
+
+        let (result, has_overflowed) = self.v(x).overflowing_sub(*self.v(opcode.y()));
+
+        self.set_with_flag(x, result, !has_overflowed);
+
+        None
+    }
+    
+    /// Vx = Vy - Vx, VF = !borrow
+    fn subn(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let x = opcode.x();
+
+        let (result, has_overflowed) = self.v(opcode.y()).overflowing_sub(*self.v(x));
+
+        self.set_with_flag(x, result, !has_overflowed);
+
+        None
+    }
+    
+    /// Vx = Vx XOR Vy
+    fn xor(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        *self.v(opcode.x()) ^= *self.v(opcode.y());
+
+        self.reset_vf();
+
+        None
+    }
+}
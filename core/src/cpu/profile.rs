@@ -0,0 +1,212 @@
+use crate::{config::Variant, disasm};
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::cmp::Reverse;
+
+/// Time spent in a subroutine, in instructions executed
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Subroutine {
+    pub calls: u64,
+
+    /// Instructions executed between the calls and their returns, including nested calls
+    pub inclusive: u64,
+
+    /// Instructions executed in the subroutine itself
+    pub exclusive: u64,
+}
+
+/// Backward jump and the code it repeats
+#[derive(Clone, Copy, Debug)]
+pub struct Loop {
+    /// Target of the jump
+    pub start: u16,
+
+    /// Address of the jump
+    pub end: u16,
+
+    /// Times the jump was taken
+    pub iterations: u64,
+
+    /// Instructions executed from `start` to `end`
+    pub instructions: u64,
+}
+
+/// Execution counts per address, instruction and call stack
+///
+/// Time is measured in instructions, the unit of the per-frame cycle budget. Calls and returns are recognized by the
+/// stack depth changing, so custom instructions that push or pop are followed too.
+pub struct Profiler {
+    variant: Variant,
+
+    /// Executions per address
+    executions: Vec<u64>,
+
+    /// Executions per opcode
+    opcodes: Vec<u64>,
+
+    /// Times each backward jump (from, to) was taken
+    back_jumps: BTreeMap<(u16, u16), u64>,
+    subroutines: BTreeMap<u16, Subroutine>,
+
+    /// Active calls (subroutine, instruction count at entry), outermost first
+    calls: Vec<(u16, u64)>,
+
+    /// Subroutines of the active calls, the key into `stacks`
+    call_stack: Vec<u16>,
+
+    /// Instructions executed per call stack
+    stacks: BTreeMap<Vec<u16>, u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub(super) fn new(variant: Variant) -> Self {
+        Self {
+            variant,
+            executions: vec![0; 0x10000],
+            opcodes: vec![0; 0x10000],
+            back_jumps: BTreeMap::new(),
+            subroutines: BTreeMap::new(),
+            calls: Vec::new(),
+            call_stack: Vec::new(),
+            stacks: BTreeMap::new(),
+            total: 0,
+        }
+    }
+
+    /// Records an instruction at `pc`, after which PC was `next_pc` and the stack depth went from `depth` to `new_depth`
+    pub(super) fn record(&mut self, pc: u16, opcode: u16, next_pc: u16, depth: usize, new_depth: usize) {
+        self.total += 1;
+        self.executions[pc as usize] += 1;
+        self.opcodes[opcode as usize] += 1;
+
+        match self.stacks.get_mut(&self.call_stack[..]) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.call_stack.clone(), 1);
+            }
+        }
+
+        if let Some(&(subroutine, _)) = self.calls.last() {
+            self.subroutines.entry(subroutine).or_default().exclusive += 1;
+        }
+
+        if new_depth > depth {
+            self.subroutines.entry(next_pc).or_default().calls += 1;
+            self.calls.push((next_pc, self.total));
+            self.call_stack.push(next_pc);
+        } else if new_depth < depth {
+            self.ret();
+        } else if next_pc <= pc {
+            *self.back_jumps.entry((pc, next_pc)).or_default() += 1;
+        }
+    }
+
+    fn ret(&mut self) {
+        if let Some((subroutine, entry)) = self.calls.pop() {
+            self.subroutines.entry(subroutine).or_default().inclusive += self.total - entry;
+            self.call_stack.pop();
+        }
+    }
+
+    /// Closes the active calls, when the stack is reset
+    pub(super) fn unwind(&mut self) {
+        while !self.calls.is_empty() {
+            self.ret();
+        }
+    }
+
+    /// Instructions executed so far
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Executions per address, indexed by address
+    pub fn executions(&self) -> &[u64] {
+        &self.executions
+    }
+
+    /// Executions per instruction mnemonic, most frequent first
+    pub fn instruction_types(&self) -> Vec<(&'static str, u64)> {
+        let mut types: BTreeMap<&str, u64> = BTreeMap::new();
+
+        for (opcode, &count) in self.opcodes.iter().enumerate().filter(|(_, count)| **count != 0) {
+            *types.entry(disasm::disassemble(opcode as u16, self.variant).mnemonic).or_default() += count;
+        }
+
+        let mut types: Vec<_> = types.into_iter().collect();
+        types.sort_by_key(|&(_, count)| Reverse(count));
+        types
+    }
+
+    /// Loops closed by backward jumps, most instructions first
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops: Vec<_> = self.back_jumps.iter().map(|(&(end, start), &iterations)| Loop {
+            start,
+            end,
+            iterations,
+            instructions: self.executions[start as usize..=end as usize].iter().sum(),
+        }).collect();
+
+        loops.sort_by_key(|hot_loop| Reverse(hot_loop.instructions));
+        loops
+    }
+
+    /// Subroutines by entry address, most inclusive time first (calls still active count up to now)
+    pub fn subroutines(&self) -> Vec<(u16, Subroutine)> {
+        let mut subroutines = self.subroutines.clone();
+
+        for &(subroutine, entry) in self.calls.iter() {
+            subroutines.entry(subroutine).or_default().inclusive += self.total - entry;
+        }
+
+        let mut subroutines: Vec<_> = subroutines.into_iter().collect();
+        subroutines.sort_by_key(|(_, subroutine)| Reverse(subroutine.inclusive));
+        subroutines
+    }
+
+    /// Instructions executed per call stack (subroutine entry addresses, outermost first; empty for the main program)
+    pub fn stacks(&self) -> impl Iterator<Item = (&[u16], u64)> {
+        self.stacks.iter().map(|(stack, &count)| (&stack[..], count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Core;
+
+    #[test]
+    fn nested_calls_and_loop() {
+        // Calls 20C three times, which calls 212, then halts at 20A
+        let rom = [
+            0x60, 0x03, 0x22, 0x0C, 0x70, 0xFF, 0x30, 0x00, 0x12, 0x02, 0x12, 0x0A,
+            0x61, 0x01, 0x22, 0x12, 0x00, 0xEE, 0x62, 0x01, 0x00, 0xEE,
+        ];
+
+        let mut core = Core::new(&rom, 0);
+
+        core.enable_profiler();
+
+        for _ in 0..4 {
+            core.frame();
+        }
+
+        let profiler = core.profiler().unwrap();
+
+        let subroutines: Vec<_> = profiler.subroutines().into_iter()
+            .map(|(addr, subroutine)| (addr, subroutine.calls, subroutine.inclusive, subroutine.exclusive))
+            .collect();
+
+        assert_eq!(subroutines, [(0x20C, 3, 15, 9), (0x212, 3, 6, 6)]);
+
+        let main_loop = profiler.loops().into_iter().find(|hot_loop| hot_loop.end == 0x208).unwrap();
+
+        assert_eq!((main_loop.start, main_loop.iterations, main_loop.instructions), (0x202, 2, 11));
+
+        let stacks: Vec<_> = profiler.stacks().collect();
+
+        assert_eq!(stacks, [(&[][..], profiler.total() - 15), (&[0x20C][..], 9), (&[0x20C, 0x212][..], 6)]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{mem::size_of, ops::{Index, IndexMut}};
+use core::{mem::size_of, ops::{Index, IndexMut}};
 
 pub const NUM_GPRS: usize = 16;
 
@@ -31,10 +31,18 @@ impl IndexMut<usize> for Gprs {
 pub struct Timer(u8);
 
 impl Timer {
+    pub fn new(counter: u8) -> Self {
+        Self(counter)
+    }
+
     pub fn counter(&mut self) -> &mut u8 {
         &mut self.0
     }
 
+    pub fn is_running(&self) -> bool {
+        self.0 > 0
+    }
+
     /// Decrements counter only if current counter is not 0
     pub fn decrement(&mut self) {
         if self.0 > 0 {
@@ -57,8 +65,8 @@ pub struct RegFile {
     /// 16 8-bit general purpose registers, V0-VF
     pub gprs: Gprs,
 
-    /// 16-bit index register
-    pub index: u16,
+    /// Index register (16-bit, 24-bit on MegaChip)
+    pub index: u32,
 
     /// Delay and sound timers
     pub delay_timer: Timer,
@@ -82,7 +90,8 @@ impl Default for RegFile {
             gprs: Gprs::default(),
             index: 0,
             delay_timer: Timer::default(),
-            sound_timer: Timer::default(),
+            // Starts silent instead of beeping until the first countdown completes
+            sound_timer: Timer::new(0),
         }
     }
 }
@@ -0,0 +1,36 @@
+use crate::cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode};
+
+/// SUPER-CHIP RPL user flag instructions, matched before the base set when `Config::rpl_flags` enables them
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 2] = [
+    OpcodeDesc(0xF075, 0xF0FF, Cpu::ld_flags),
+    OpcodeDesc(0xF085, 0xF0FF, Cpu::ldv_flags),
+];
+
+impl Cpu {
+    /// Number of registers Fx75/Fx85 copy; x past the last flag is clamped to it
+    fn flag_count(&self, opcode: Opcode) -> usize {
+        usize::min(opcode.x() + 1, self.rpl_flags.count())
+    }
+
+    /// Flags 0..=x = V0..=Vx
+    fn ld_flags(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let count = self.flag_count(opcode);
+
+        for (i, flag) in self.user_flags[..count].iter_mut().enumerate() {
+            *flag = self.regfile.gprs[i];
+        }
+
+        None
+    }
+
+    /// V0..=Vx = flags 0..=x
+    fn ldv_flags(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let count = self.flag_count(opcode);
+
+        for (i, flag) in self.user_flags[..count].iter().enumerate() {
+            self.regfile.gprs[i] = *flag;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    bus::{Access, Address, Bus, BusFault, FaultKind},
+    cdp1802::{Cdp1802, System},
+    cpu::{Cpu, CpuEvent, opcode::Opcode},
+};
+
+/// Where the VIP interpreter keeps V0-VF
+pub const VIP_REGISTERS: u16 = 0xEF0;
+
+/// Top of the VIP interpreter's machine code stack (R2)
+pub const VIP_MACHINE_STACK: u16 = 0xECF;
+
+/// Display page the VIP interpreter keeps in RB.1
+pub const VIP_DISPLAY_PAGE: u8 = 0x0F;
+
+/// SEP R4, which returns to the interpreter's fetch loop
+const RETURN: u8 = 0xD4;
+
+/// Longest machine code subroutine, in instructions, before it is considered stuck
+const MAX_STEPS: usize = 1 << 20;
+
+/// Machine code accesses go through the Chip-8 bus, so they show up in the access log
+struct SysBus<'a>(&'a mut Bus);
+
+impl<'a> System for SysBus<'a> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.0.read_byte(Address::new(addr))
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.0.write_byte(Address::new(addr), data);
+    }
+}
+
+impl Cpu {
+    /// Call the CDP1802 machine code subroutine at nnn, which returns with D4
+    ///
+    /// The routine sees the registers where the VIP interpreter keeps them: V0-VF in memory at `VIP_REGISTERS`, I in
+    /// RA, the Chip-8 PC in R5 and the stack in R2. Changes to those are copied back when it returns. A routine that
+    /// idles or never returns raises a `FaultKind::NoReturn` fault instead and leaves the Chip-8 registers as they were.
+    pub(super) fn sys(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        for i in 0..16 {
+            let vx = *self.v(i);
+
+            self.bus.write_byte(Address::new(VIP_REGISTERS + i as u16), vx);
+        }
+
+        let mut cdp1802 = Cdp1802 { p: 3, x: 2, ..Cdp1802::default() };
+
+        // With the VIP memory map the machine stack continues below the Chip-8 call stack, as on the VIP
+        cdp1802.r[2] = self.stack.sp.map_or(VIP_MACHINE_STACK, |sp| sp - 1);
+        cdp1802.r[3] = opcode.nnn();
+        cdp1802.r[5] = *self.pc();
+        cdp1802.r[0xA] = *self.i() as u16;
+        cdp1802.r[0xB] = (VIP_DISPLAY_PAGE as u16) << 8;
+
+        let mut sys = SysBus(&mut self.bus);
+        let mut returned = false;
+
+        for _ in 0..MAX_STEPS {
+            if sys.0.peek_byte(Address::new(cdp1802.r[cdp1802.p as usize])) == RETURN {
+                returned = true;
+
+                break;
+            }
+
+            // Nothing raises an interrupt or DMA request here, so an idle CPU never wakes up
+            if cdp1802.idle {
+                break;
+            }
+
+            cdp1802.step(&mut sys);
+        }
+
+        if !returned {
+            let addr = cdp1802.r[cdp1802.p as usize] as u32;
+
+            return Some(CpuEvent::Fault(BusFault { kind: FaultKind::NoReturn, addr, access: Access::Read }));
+        }
+
+        for i in 0..16 {
+            *self.v(i) = self.bus.read_byte(Address::new(VIP_REGISTERS + i as u16));
+        }
+
+        *self.pc() = cdp1802.r[5];
+        *self.i() = (*self.i() & !0xFFFF) | cdp1802.r[0xA] as u32;
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Core, bus::FaultKind};
+
+    #[test]
+    fn routine_returns() {
+        // 0204 calls D4 (SEP R4) at 0x204, then 1202 loops
+        let mut core = Core::new(&[0x02, 0x04, 0x12, 0x02, 0xD4], 0);
+
+        core.frame();
+
+        assert!(core.take_fault().is_none());
+    }
+
+    #[test]
+    fn routine_idles() {
+        // 0000 calls address 0, which holds IDL
+        let mut core = Core::new(&[0x00, 0x00], 0);
+
+        core.frame();
+
+        let fault = core.take_fault().unwrap();
+
+        assert_eq!((fault.kind, fault.addr), (FaultKind::NoReturn, 0x001));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    bus::Address,
+    cpu::{Cpu, CpuEvent, OpcodeDesc, opcode::Opcode, regfile::VF},
+    display::Display,
+};
+
+/// Lowest address of the call stack, which grows down from `VIP_STACK_END`
+pub const VIP_STACK: u16 = 0xEA0;
+pub const VIP_STACK_END: u16 = 0xED0;
+
+/// 64x32 display, one bit per pixel, 8 bytes per row with the leftmost pixel in the high bit
+pub const VIP_FRAMEBUFFER: u16 = 0xF00;
+
+/// Handlers drawing into the framebuffer in memory, matched before the base set
+pub(super) const OPCODE_DESCS: [OpcodeDesc; 2] = [
+    OpcodeDesc(0x00E0, 0xFFFF, Cpu::cls_vip),
+    OpcodeDesc(0xD000, 0xF000, Cpu::drw_vip),
+];
+
+const ROW_BYTES: usize = Display::WIDTH / 8;
+
+impl Cpu {
+    /// Address of the framebuffer byte holding pixel (x, y)
+    fn framebuffer_address(x: usize, y: usize) -> Address {
+        Address::new(VIP_FRAMEBUFFER + (ROW_BYTES * y + x / 8) as u16)
+    }
+
+    /// Redraws the display from the framebuffer in memory
+    pub(super) fn refresh_display(&mut self) {
+        for y in 0..Display::HEIGHT {
+            for x in 0..Display::WIDTH {
+                let byte = self.bus.peek_byte(Self::framebuffer_address(x, y));
+
+                self.display[Display::WIDTH * y + x] = Display::COLOR_WHITE * (byte >> (7 - x % 8) & 1) as u32;
+            }
+        }
+    }
+
+    /// Clear the framebuffer
+    fn cls_vip(&mut self, _opcode: Opcode) -> Option<CpuEvent> {
+        for offset in 0..(ROW_BYTES * Display::HEIGHT) as u16 {
+            self.bus.write_byte(Address::new(VIP_FRAMEBUFFER + offset), 0);
+        }
+
+        self.refresh_display();
+
+        None
+    }
+
+    /// Draw sprite into the framebuffer
+    fn drw_vip(&mut self, opcode: Opcode) -> Option<CpuEvent> {
+        let (width, height) = (Display::WIDTH, Display::HEIGHT);
+
+        let (index, x, y) = (
+            *self.i(),
+            *self.v(opcode.x()) as usize % width,
+            *self.v(opcode.y()) as usize % height,
+        );
+
+        let mut has_collided = false;
+
+        for n in 0..opcode.n() {
+            let sprite = self.bus.read_byte(Address::new(index.wrapping_add(n as u32)));
+            let yn = y + n;
+
+            // Y past the bottom causes clipping (or wraps around without the clip quirk)
+            if yn >= height && self.quirks.clip {
+                break;
+            }
+
+            for i in 0..8 {
+                let xi = x + i;
+
+                // X past the right edge causes clipping (or wraps around without the clip quirk)
+                if xi >= width && self.quirks.clip {
+                    break;
+                }
+
+                if sprite & (0x80 >> i) == 0 {
+                    continue;
+                }
+
+                let (xi, yn) = (xi % width, yn % height);
+                let addr = Self::framebuffer_address(xi, yn);
+
+                let bit = 0x80 >> (xi % 8);
+                let byte = self.bus.read_byte(addr);
+
+                has_collided |= byte & bit != 0;
+
+                self.bus.write_byte(addr, byte ^ bit);
+            }
+        }
+
+        *self.v(VF) = has_collided as u8;
+
+        self.refresh_display();
+
+        Some(CpuEvent::Draw)
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::Variant;
+
+use alloc::{format, string::String};
+use core::fmt;
+
+/// Decoded instruction, in Cowgod's notation (`LD Vx, kk`, `DRW Vx, Vy, n`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub mnemonic: &'static str,
+    pub operands: String,
+}
+
+impl Instruction {
+    fn new(mnemonic: &'static str, operands: String) -> Self {
+        Self { mnemonic, operands }
+    }
+
+    fn bare(mnemonic: &'static str) -> Self {
+        Self::new(mnemonic, String::new())
+    }
+
+    /// Returns true for opcodes no instruction of the variant handles
+    pub fn is_unknown(&self) -> bool {
+        self.mnemonic == "DW"
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operands.is_empty() {
+            true => write!(f, "{}", self.mnemonic),
+            false => write!(f, "{} {}", self.mnemonic, self.operands),
+        }
+    }
+}
+
+/// Decodes an opcode the way the CPU of `variant` matches it (variant instructions before the base set)
+///
+/// Opcodes nothing handles come out as `DW nnnn`. MegaChip's 01nn only shows the high byte of the address, which
+/// continues in the next word.
+pub fn disassemble(opcode: u16, variant: Variant) -> Instruction {
+    let variant_instruction = match variant {
+        Variant::Chip8E => chip8e(opcode),
+        Variant::Chip8I => chip8i(opcode),
+        Variant::Chip8X => chip8x(opcode),
+        Variant::Hires if opcode == 0x0230 => Some(Instruction::bare("CLS")),
+        Variant::MegaChip => megachip(opcode),
+        _ => None,
+    };
+
+    variant_instruction.unwrap_or_else(|| base(opcode))
+}
+
+fn base(opcode: u16) -> Instruction {
+    let (x, y, n) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF, opcode & 0xF);
+    let (kk, nnn) = (opcode & 0xFF, opcode & 0xFFF);
+
+    let (mnemonic, operands) = match (opcode >> 12, n, kk) {
+        (0x0, _, _) if opcode == 0x00E0 => ("CLS", String::new()),
+        (0x0, _, _) if opcode == 0x00EE => ("RET", String::new()),
+        (0x0, _, _) => ("SYS", format!("{:03X}", nnn)),
+        (0x1, _, _) => ("JP", format!("{:03X}", nnn)),
+        (0x2, _, _) => ("CALL", format!("{:03X}", nnn)),
+        (0x3, _, _) => ("SE", format!("V{:X}, {:02X}", x, kk)),
+        (0x4, _, _) => ("SNE", format!("V{:X}, {:02X}", x, kk)),
+        (0x5, 0x0, _) => ("SE", format!("V{:X}, V{:X}", x, y)),
+        (0x6, _, _) => ("LD", format!("V{:X}, {:02X}", x, kk)),
+        (0x7, _, _) => ("ADD", format!("V{:X}, {:02X}", x, kk)),
+        (0x8, 0x0, _) => ("LD", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x1, _) => ("OR", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x2, _) => ("AND", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x3, _) => ("XOR", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x4, _) => ("ADD", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x5, _) => ("SUB", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x6, _) => ("SHR", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0x7, _) => ("SUBN", format!("V{:X}, V{:X}", x, y)),
+        (0x8, 0xE, _) => ("SHL", format!("V{:X}, V{:X}", x, y)),
+        (0x9, 0x0, _) => ("SNE", format!("V{:X}, V{:X}", x, y)),
+        (0xA, _, _) => ("LD", format!("I, {:03X}", nnn)),
+        (0xB, _, _) => ("JP", format!("V0, {:03X}", nnn)),
+        (0xC, _, _) => ("RND", format!("V{:X}, {:02X}", x, kk)),
+        (0xD, _, _) => ("DRW", format!("V{:X}, V{:X}, {:X}", x, y, n)),
+        (0xE, _, 0x9E) => ("SKP", format!("V{:X}", x)),
+        (0xE, _, 0xA1) => ("SKNP", format!("V{:X}", x)),
+        (0xF, _, 0x07) => ("LD", format!("V{:X}, DT", x)),
+        (0xF, _, 0x0A) => ("LD", format!("V{:X}, K", x)),
+        (0xF, _, 0x15) => ("LD", format!("DT, V{:X}", x)),
+        (0xF, _, 0x18) => ("LD", format!("ST, V{:X}", x)),
+        (0xF, _, 0x1E) => ("ADD", format!("I, V{:X}", x)),
+        (0xF, _, 0x29) => ("LD", format!("F, V{:X}", x)),
+        (0xF, _, 0x30) => ("LD", format!("HF, V{:X}", x)),
+        (0xF, _, 0x33) => ("LD", format!("B, V{:X}", x)),
+        (0xF, _, 0x55) => ("LD", format!("[I], V{:X}", x)),
+        (0xF, _, 0x65) => ("LD", format!("V{:X}, [I]", x)),
+        (0xF, _, 0x75) => ("LD", format!("R, V{:X}", x)),
+        (0xF, _, 0x85) => ("LD", format!("V{:X}, R", x)),
+        _ => ("DW", format!("{:04X}", opcode)),
+    };
+
+    Instruction::new(mnemonic, operands)
+}
+
+fn chip8e(opcode: u16) -> Option<Instruction> {
+    let (x, y, kk) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF, opcode & 0xFF);
+
+    let instruction = match (opcode >> 12, opcode & 0xF, kk) {
+        _ if opcode == 0x00ED => Instruction::bare("STOP"),
+        _ if opcode == 0x00F2 => Instruction::bare("NOP"),
+        _ if opcode == 0x0151 => Instruction::bare("WAITDT"),
+        _ if opcode == 0x0188 => Instruction::bare("SKIP"),
+        (0x5, 0x1, _) => Instruction::new("SGT", format!("V{:X}, V{:X}", x, y)),
+        (0x5, 0x2, _) => Instruction::new("LD", format!("[I], V{:X}-V{:X}", x, y)),
+        (0x5, 0x3, _) => Instruction::new("LD", format!("V{:X}-V{:X}, [I]", x, y)),
+        (0xB, _, _) if x == 0xB => Instruction::new("JB", format!("{:02X}", kk)),
+        (0xB, _, _) if x == 0xF => Instruction::new("JF", format!("{:02X}", kk)),
+        (0xF, _, 0x03) => Instruction::new("OUT", format!("V{:X}", x)),
+        (0xF, _, 0x1B) => Instruction::new("SKB", format!("V{:X}", x)),
+        (0xF, _, 0x4F) => Instruction::new("LDW", format!("DT, V{:X}", x)),
+        (0xF, _, 0xE3) => Instruction::new("INP", format!("V{:X}", x)),
+        (0xF, _, 0xE7) => Instruction::new("INPN", format!("V{:X}", x)),
+        _ => return None,
+    };
+
+    Some(instruction)
+}
+
+fn chip8i(opcode: u16) -> Option<Instruction> {
+    let x = (opcode >> 8) & 0xF;
+
+    let mnemonic = match (opcode >> 12, opcode & 0xFF) {
+        (0xF, 0x03) => "OUT",
+        (0xF, 0xE3) => "INP",
+        (0xF, 0xE7) => "INPN",
+        _ => return None,
+    };
+
+    Some(Instruction::new(mnemonic, format!("V{:X}", x)))
+}
+
+fn chip8x(opcode: u16) -> Option<Instruction> {
+    let (x, y, n, kk) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF, opcode & 0xF, opcode & 0xFF);
+
+    let instruction = match (opcode >> 12, n, kk) {
+        _ if opcode == 0x02A0 => Instruction::bare("BGND"),
+        (0x5, 0x1, _) => Instruction::new("ADDBCD", format!("V{:X}, V{:X}", x, y)),
+        (0xB, _, _) => Instruction::new("COL", format!("V{:X}, V{:X}, {:X}", x, y, n)),
+        (0xE, _, 0xF2) => Instruction::new("SKP2", format!("V{:X}", x)),
+        (0xE, _, 0xF5) => Instruction::new("SKNP2", format!("V{:X}", x)),
+        (0xF, _, 0xF8) => Instruction::new("OUT", format!("V{:X}", x)),
+        (0xF, _, 0xFB) => Instruction::new("INP", format!("V{:X}", x)),
+        _ => return None,
+    };
+
+    Some(instruction)
+}
+
+fn megachip(opcode: u16) -> Option<Instruction> {
+    let (n, kk) = (opcode & 0xF, opcode & 0xFF);
+
+    let instruction = match opcode >> 8 {
+        _ if opcode == 0x0010 => Instruction::bare("MEGAOFF"),
+        _ if opcode == 0x0011 => Instruction::bare("MEGAON"),
+        0x00 if opcode & 0xFFF0 == 0x00B0 => Instruction::new("SCRU", format!("{:X}", n)),
+        0x00 if opcode & 0xFFF0 == 0x00C0 => Instruction::new("SCD", format!("{:X}", n)),
+        0x00 if opcode == 0x00FB => Instruction::bare("SCR"),
+        0x00 if opcode == 0x00FC => Instruction::bare("SCL"),
+        0x00 if opcode == 0x00FD => Instruction::bare("EXIT"),
+        0x00 if opcode == 0x00FE => Instruction::bare("LOW"),
+        0x00 if opcode == 0x00FF => Instruction::bare("HIGH"),
+        0x01 => Instruction::new("LDHI", format!("I, {:02X}xxxx", kk)),
+        0x02 => Instruction::new("LDPAL", format!("{:02X}", kk)),
+        0x03 => Instruction::new("SPRW", format!("{:02X}", kk)),
+        0x04 => Instruction::new("SPRH", format!("{:02X}", kk)),
+        0x05 => Instruction::new("ALPHA", format!("{:02X}", kk)),
+        0x06 if kk & 0xF0 == 0 => Instruction::new("DIGISND", format!("{:X}", n)),
+        0x07 if kk == 0 => Instruction::bare("STOPSND"),
+        0x08 if kk & 0xF0 == 0 => Instruction::new("BMODE", format!("{:X}", n)),
+        0x09 => Instruction::new("CCOL", format!("{:02X}", kk)),
+        _ => return None,
+    };
+
+    Some(instruction)
+}
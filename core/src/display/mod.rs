@@ -0,0 +1,140 @@
+use alloc::{vec, vec::Vec};
+use core::ops::{Index, IndexMut};
+
+pub struct Display {
+    width: usize,
+    height: usize,
+
+    /// Row-major, `width * height` pixels
+    pixels: Vec<u32>,
+
+    /// Palette index of every pixel, for MegaChip collisions
+    indices: Vec<u8>,
+
+    /// Last frame presented by a MegaChip 00E0
+    front: Vec<u32>,
+
+    /// Foreground color of every color zone (CHIP-8X)
+    zones: Vec<u8>,
+
+    /// Background color (CHIP-8X)
+    background: u8,
+}
+
+impl Display {
+    /// Standard Chip-8 geometry
+    pub const WIDTH: usize = 64;
+    pub const HEIGHT: usize = 32;
+
+    /// SUPER-CHIP high resolution geometry
+    pub const SCHIP_WIDTH: usize = 128;
+    pub const SCHIP_HEIGHT: usize = 64;
+
+    /// MegaChip geometry
+    pub const MEGA_WIDTH: usize = 256;
+    pub const MEGA_HEIGHT: usize = 192;
+
+    pub const COLOR_WHITE: u32 = 0xFFFFFFFF;
+
+    /// Width in pixels of a color zone; zones are one pixel tall
+    pub const ZONE_WIDTH: usize = 8;
+    pub const ZONE_COLUMNS: usize = Self::WIDTH / Self::ZONE_WIDTH;
+
+    /// VP-590 foreground colors (0RGB), indexed by the 3-bit color number
+    pub const FOREGROUND_COLORS: [u32; 8] = [
+        0x00000000, 0x00FF0000, 0x000000FF, 0x00FF00FF,
+        0x0000FF00, 0x00FFFF00, 0x0000FFFF, 0x00FFFFFF,
+    ];
+
+    /// VP-590 background colors (0RGB), in the order 02A0 cycles through them
+    pub const BACKGROUND_COLORS: [u32; 4] = [0x00000080, 0x00000000, 0x00008000, 0x00800000];
+
+    /// Zones start out red on dark blue
+    const DEFAULT_ZONE_COLOR: u8 = 1;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+            indices: vec![0; width * height],
+            front: vec![0; width * height],
+            zones: vec![Self::DEFAULT_ZONE_COLOR; Self::ZONE_COLUMNS * height],
+            background: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    pub fn as_slice(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// Returns the pixels and their palette indices
+    pub fn layers_mut(&mut self) -> (&mut [u32], &mut [u8]) {
+        (&mut self.pixels, &mut self.indices)
+    }
+
+    /// Returns the last presented frame
+    pub fn front(&self) -> &[u32] {
+        &self.front
+    }
+
+    /// Shows the pixels drawn so far (MegaChip draws off-screen)
+    pub fn present(&mut self) {
+        self.front.copy_from_slice(&self.pixels);
+    }
+
+    /// Returns the foreground color number of the zone holding pixel (x, y)
+    pub fn zone_color(&self, x: usize, y: usize) -> u8 {
+        self.zones[Self::ZONE_COLUMNS * (y % self.height) + (x % Self::WIDTH) / Self::ZONE_WIDTH]
+    }
+
+    /// Sets the foreground color of the zone at zone column `column` and pixel row `y` (both wrap around)
+    pub fn set_zone_color(&mut self, column: usize, y: usize, color: u8) {
+        self.zones[Self::ZONE_COLUMNS * (y % self.height) + column % Self::ZONE_COLUMNS] = color & 0x7;
+    }
+
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    /// Advances to the next background color
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % Self::BACKGROUND_COLORS.len() as u8;
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new(Self::WIDTH, Self::HEIGHT)
+    }
+}
+
+impl Index<usize> for Display {
+    type Output = u32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.pixels[index]
+    }
+}
+
+impl IndexMut<usize> for Display {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.pixels[index]
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{config::{Config, Variant}, disasm};
+
+use alloc::{collections::{BTreeMap, BTreeSet}, vec, vec::Vec};
+use core::ops::Range;
+
+/// How control leaves a basic block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Runs into the block at the address
+    Fallthrough(u16),
+
+    /// 1nnn and the CHIP-8E relative branches
+    Jump(u16),
+
+    /// 2nnn to the subroutine at the address; it continues with a `Fallthrough` when the subroutine returns
+    Call(u16),
+
+    /// The next instruction is skipped, continuing at the address
+    Skip(u16),
+
+    /// 00EE
+    Return,
+
+    /// Target only known at run time (Bnnn, CHIP-8E Fx1B)
+    Computed,
+
+    /// CHIP-8E 00ED
+    Stop,
+
+    /// Opcode no instruction handles
+    Invalid,
+}
+
+/// Straight-line run of instructions, entered at the top and left at the bottom
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub start: u16,
+
+    /// Address after the last instruction
+    pub end: u16,
+
+    /// Instruction addresses, in order
+    pub instructions: Vec<u16>,
+    pub exits: Vec<Exit>,
+}
+
+/// Store to memory that holds reachable code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeWrite {
+    /// Address of the store instruction
+    pub at: u16,
+
+    /// Addresses written
+    pub target: Range<u32>,
+}
+
+/// Control-flow graph of a program, reconstructed without running it
+///
+/// Only code reachable from the entry point through jumps, calls, skips and fallthroughs is found. Computed jumps end
+/// the search along their path, and self-modifying writes may turn code into something else at run time; both are
+/// reported so the graph can be read with care.
+#[derive(Clone, Debug, Default)]
+pub struct ControlFlowGraph {
+    pub entry: u16,
+
+    /// Basic blocks by start address
+    pub blocks: BTreeMap<u16, Block>,
+
+    /// Block starts of every subroutine by entry address; the main program is the one at `entry`
+    pub subroutines: BTreeMap<u16, BTreeSet<u16>>,
+
+    /// Addresses of Bnnn and other jumps with run-time targets
+    pub computed_jumps: Vec<u16>,
+
+    /// Stores whose target (with I known statically) overlaps reachable code
+    pub code_writes: Vec<CodeWrite>,
+}
+
+/// Control-flow effect of one instruction
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Next,
+    Jump(u16),
+    Call(u16),
+    Skip,
+    SkipAlways,
+    Return,
+    Computed,
+    Stop,
+    Invalid,
+}
+
+/// Effect of one instruction on I
+#[derive(Clone, Copy)]
+enum IndexEffect {
+    Keep,
+    Set(u32),
+
+    /// I + n with the load/store quirk off, unchanged with it on
+    Advance(u32),
+    Unknown,
+}
+
+struct Decoded {
+    len: u16,
+    flow: Flow,
+    index: IndexEffect,
+
+    /// Bytes written from I (relative range)
+    store: Option<Range<u32>>,
+}
+
+fn decode(addr: u16, opcode: u16, variant: Variant) -> Decoded {
+    let (x, y, kk, nnn) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF, opcode & 0xFF, opcode & 0xFFF);
+    let next = addr.wrapping_add(2);
+
+    let mut decoded = Decoded { len: 2, flow: Flow::Next, index: IndexEffect::Keep, store: None };
+
+    if disasm::disassemble(opcode, variant).is_unknown() {
+        decoded.flow = Flow::Invalid;
+
+        return decoded;
+    }
+
+    match (variant, opcode >> 12, opcode & 0xF, kk) {
+        (Variant::Chip8E, _, _, _) if opcode == 0x00ED => decoded.flow = Flow::Stop,
+        (Variant::Chip8E, _, _, _) if opcode == 0x0188 => decoded.flow = Flow::SkipAlways,
+        (Variant::Chip8E, 0x5, 0x1, _) => decoded.flow = Flow::Skip,
+        (Variant::Chip8E, 0x5, 0x2, _) => {
+            decoded.store = Some(0..x.abs_diff(y) as u32 + 1);
+            decoded.index = IndexEffect::Advance(x.abs_diff(y) as u32 + 1);
+        }
+        (Variant::Chip8E, 0x5, 0x3, _) => decoded.index = IndexEffect::Advance(x.abs_diff(y) as u32 + 1),
+        (Variant::Chip8E, 0xB, _, _) if x == 0xB => decoded.flow = Flow::Jump(next.wrapping_sub(kk)),
+        (Variant::Chip8E, 0xB, _, _) if x == 0xF => decoded.flow = Flow::Jump(next.wrapping_add(kk)),
+        (Variant::Chip8E, 0xF, _, 0x1B) => decoded.flow = Flow::Computed,
+        // Fx4F only waits on itself and FxE7 reads the port: both continue with the next instruction
+        (Variant::Chip8E, 0xF, _, 0x4F) | (Variant::Chip8E, 0xF, _, 0xE7) => {}
+        (Variant::Chip8X, 0x5, 0x1, _) | (Variant::Chip8X, 0xB, _, _) => {}
+        (Variant::Chip8X, 0xE, _, 0xF2) | (Variant::Chip8X, 0xE, _, 0xF5) => decoded.flow = Flow::Skip,
+        (Variant::MegaChip, _, _, _) if opcode == 0x00FD => decoded.flow = Flow::Stop,
+        (Variant::MegaChip, 0x0, _, _) if opcode >> 8 == 0x01 => {
+            decoded.len = 4;
+            decoded.index = IndexEffect::Unknown;
+        }
+        (_, 0x0, _, _) if opcode == 0x00EE => decoded.flow = Flow::Return,
+        (_, 0x1, _, _) => decoded.flow = Flow::Jump(nnn),
+        (_, 0x2, _, _) => decoded.flow = Flow::Call(nnn),
+        (_, 0x3, _, _) | (_, 0x4, _, _) | (_, 0x5, _, _) | (_, 0x9, _, _) => decoded.flow = Flow::Skip,
+        (_, 0xA, _, _) => decoded.index = IndexEffect::Set(nnn as u32),
+        (_, 0xB, _, _) => decoded.flow = Flow::Computed,
+        (_, 0xE, _, _) => decoded.flow = Flow::Skip,
+        (_, 0xF, _, 0x1E) | (_, 0xF, _, 0x29) | (_, 0xF, _, 0x30) => decoded.index = IndexEffect::Unknown,
+        (_, 0xF, _, 0x33) => decoded.store = Some(0..3),
+        (_, 0xF, _, 0x55) => {
+            decoded.store = Some(0..x as u32 + 1);
+            decoded.index = IndexEffect::Advance(x as u32 + 1);
+        }
+        (_, 0xF, _, 0x65) => decoded.index = IndexEffect::Advance(x as u32 + 1),
+        _ => {}
+    }
+
+    decoded
+}
+
+/// Statically known value of I at an instruction (`None` if it depends on the path or on run-time data)
+type IndexState = Option<u32>;
+
+/// Builds the control-flow graph of `program` as loaded and started by `config`
+pub fn analyze(program: &[u8], config: &Config) -> ControlFlowGraph {
+    let start = config.load_address as usize;
+    let variant = config.variant;
+
+    let opcode_at = |addr: u16| -> Option<u16> {
+        let offset = (addr as usize).checked_sub(start)?;
+
+        Some(u16::from_be_bytes([*program.get(offset)?, *program.get(offset + 1)?]))
+    };
+
+    // Reachable instructions and the value of I on entry, to a fixed point
+    let mut instructions: BTreeMap<u16, (Decoded, IndexState)> = BTreeMap::new();
+    let mut worklist = vec![(config.entry_point, None)];
+    let mut leaders = BTreeSet::from([config.entry_point]);
+    let mut routines = BTreeSet::from([config.entry_point]);
+
+    while let Some((addr, index)) = worklist.pop() {
+        let index = match instructions.get_mut(&addr) {
+            Some((_, state)) if *state == index || state.is_none() => continue,
+            Some((_, state)) => {
+                *state = None;
+
+                None
+            }
+            None => match opcode_at(addr) {
+                Some(opcode) => {
+                    instructions.insert(addr, (decode(addr, opcode, variant), index));
+
+                    index
+                }
+                None => continue,
+            },
+        };
+
+        let decoded = &instructions[&addr].0;
+        let next = addr.wrapping_add(decoded.len);
+
+        let index_after = match decoded.index {
+            IndexEffect::Keep => index,
+            IndexEffect::Set(value) => Some(value),
+            IndexEffect::Advance(_) if config.quirks.load_store => index,
+            IndexEffect::Advance(n) => index.map(|i| i.wrapping_add(n)),
+            IndexEffect::Unknown => None,
+        };
+
+        match decoded.flow {
+            Flow::Next => worklist.push((next, index_after)),
+            Flow::Jump(target) => {
+                leaders.insert(target);
+                worklist.push((target, index_after));
+            }
+            Flow::Call(target) => {
+                leaders.extend([target, next]);
+                routines.insert(target);
+
+                // The subroutine may change I before it returns
+                worklist.extend([(target, index_after), (next, None)]);
+            }
+            Flow::Skip => {
+                leaders.extend([next, next.wrapping_add(2)]);
+                worklist.extend([(next, index_after), (next.wrapping_add(2), index_after)]);
+            }
+            Flow::SkipAlways => {
+                leaders.insert(next.wrapping_add(2));
+                worklist.push((next.wrapping_add(2), index_after));
+            }
+            Flow::Return | Flow::Computed | Flow::Stop | Flow::Invalid => {}
+        }
+    }
+
+    let mut graph = ControlFlowGraph { entry: config.entry_point, ..Default::default() };
+
+    // Split the reachable instructions into blocks at the leaders and after every change of flow
+    let mut current: Option<Block> = None;
+
+    for (&addr, (decoded, _)) in instructions.iter() {
+        let block = match current.take() {
+            Some(block) if block.end == addr && !leaders.contains(&addr) => block,
+            Some(mut block) => {
+                block.exits.push(Exit::Fallthrough(block.end));
+                graph.blocks.insert(block.start, block);
+
+                Block { start: addr, end: addr, instructions: Vec::new(), exits: Vec::new() }
+            }
+            None => Block { start: addr, end: addr, instructions: Vec::new(), exits: Vec::new() },
+        };
+
+        let mut block = block;
+        let next = addr.wrapping_add(decoded.len);
+
+        block.instructions.push(addr);
+        block.end = next;
+
+        let exits = match decoded.flow {
+            Flow::Next => {
+                current = Some(block);
+
+                continue;
+            }
+            Flow::Jump(target) => vec![Exit::Jump(target)],
+            Flow::Call(target) => vec![Exit::Call(target), Exit::Fallthrough(next)],
+            Flow::Skip => vec![Exit::Fallthrough(next), Exit::Skip(next.wrapping_add(2))],
+            Flow::SkipAlways => vec![Exit::Skip(next.wrapping_add(2))],
+            Flow::Return => vec![Exit::Return],
+            Flow::Computed => {
+                graph.computed_jumps.push(addr);
+
+                vec![Exit::Computed]
+            }
+            Flow::Stop => vec![Exit::Stop],
+            Flow::Invalid => vec![Exit::Invalid],
+        };
+
+        block.exits = exits;
+        graph.blocks.insert(block.start, block);
+    }
+
+    if let Some(mut block) = current {
+        block.exits.push(Exit::Fallthrough(block.end));
+        graph.blocks.insert(block.start, block);
+    }
+
+    // Stores into reachable code, with I known
+    let code: BTreeSet<u32> = instructions.iter().flat_map(|(&addr, (decoded, _))| (0..decoded.len as u32).map(move |i| addr as u32 + i)).collect();
+
+    for (&addr, (decoded, index)) in instructions.iter() {
+        if let (Some(store), Some(index)) = (&decoded.store, index) {
+            let target = index + store.start..index + store.end;
+
+            if target.clone().any(|byte| code.contains(&byte)) {
+                graph.code_writes.push(CodeWrite { at: addr, target });
+            }
+        }
+    }
+
+    // Blocks of each routine, following everything but calls
+    for &routine in routines.iter() {
+        let mut members = BTreeSet::new();
+        let mut pending = vec![routine];
+
+        while let Some(start) = pending.pop() {
+            let block = match graph.blocks.get(&start) {
+                Some(block) if members.insert(start) => block,
+                _ => continue,
+            };
+
+            for exit in block.exits.iter() {
+                if let Exit::Fallthrough(target) | Exit::Jump(target) | Exit::Skip(target) = *exit {
+                    pending.push(target);
+                }
+            }
+        }
+
+        graph.subroutines.insert(routine, members);
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Calls 208, skips 204 if V0 is 1, then jumps to 20C to store V0 over the call and jump to 300 + V0
+    const PROGRAM: [u8; 20] = [
+        0x22, 0x08, 0x30, 0x01, 0x60, 0x01, 0x12, 0x0C, 0x61, 0x01,
+        0x00, 0xEE, 0xA2, 0x00, 0xF0, 0x55, 0xB3, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn blocks_and_subroutines() {
+        let graph = analyze(&PROGRAM, &Config::default());
+
+        let exits: Vec<(u16, Vec<u16>, Vec<Exit>)> = graph.blocks.values()
+            .map(|block| (block.start, block.instructions.clone(), block.exits.clone()))
+            .collect();
+
+        assert_eq!(exits, [
+            (0x200, vec![0x200], vec![Exit::Call(0x208), Exit::Fallthrough(0x202)]),
+            (0x202, vec![0x202], vec![Exit::Fallthrough(0x204), Exit::Skip(0x206)]),
+            (0x204, vec![0x204], vec![Exit::Fallthrough(0x206)]),
+            (0x206, vec![0x206], vec![Exit::Jump(0x20C)]),
+            (0x208, vec![0x208, 0x20A], vec![Exit::Return]),
+            (0x20C, vec![0x20C, 0x20E, 0x210], vec![Exit::Computed]),
+        ]);
+
+        assert_eq!(graph.subroutines[&0x200], BTreeSet::from([0x200, 0x202, 0x204, 0x206, 0x20C]));
+        assert_eq!(graph.subroutines[&0x208], BTreeSet::from([0x208]));
+        assert_eq!(graph.subroutines.len(), 2);
+    }
+
+    #[test]
+    fn computed_jumps_and_code_writes() {
+        let graph = analyze(&PROGRAM, &Config::default());
+
+        assert_eq!(graph.computed_jumps, [0x210]);
+        assert_eq!(graph.code_writes, [CodeWrite { at: 0x20E, target: 0x200..0x201 }]);
+    }
+}
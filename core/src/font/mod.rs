@@ -0,0 +1,221 @@
+use crate::config::ConfigError;
+
+use core::{fmt, str::FromStr};
+
+/// Hexadecimal digit glyphs pointed at by Fx29, plus the SCHIP large digits stored after them
+///
+/// Memory holds the 16 small glyphs (4x5, one byte per row) at the font address, followed by the large ones (8x10).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    pub small: [u8; Font::SMALL_SIZE],
+    pub big: [u8; Font::BIG_SIZE],
+}
+
+impl Font {
+    pub const SMALL_GLYPH_SIZE: usize = 5;
+    pub const BIG_GLYPH_SIZE: usize = 10;
+    pub const NUM_GLYPHS: usize = 16;
+
+    pub const SMALL_SIZE: usize = Self::SMALL_GLYPH_SIZE * Self::NUM_GLYPHS;
+    pub const BIG_SIZE: usize = Self::BIG_GLYPH_SIZE * Self::NUM_GLYPHS;
+
+    /// Bytes taken in memory
+    pub const SIZE: usize = Self::SMALL_SIZE + Self::BIG_SIZE;
+
+    /// SUPER-CHIP 1.1 large digits; it has no A-F, which are left blank
+    pub const SCHIP_BIG: [u8; Self::BIG_SIZE] = [
+        0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C,
+        0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C,
+        0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF,
+        0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C,
+        0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06,
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C,
+        0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C,
+        0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60,
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C,
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// COSMAC VIP interpreter
+    pub const VIP: Font = Font {
+        small: [
+            0xF0, 0x90, 0x90, 0x90, 0xF0,
+            0x60, 0x20, 0x20, 0x20, 0x70,
+            0xF0, 0x10, 0xF0, 0x80, 0xF0,
+            0xF0, 0x10, 0xF0, 0x10, 0xF0,
+            0xA0, 0xA0, 0xF0, 0x20, 0x20,
+            0xF0, 0x80, 0xF0, 0x10, 0xF0,
+            0xF0, 0x80, 0xF0, 0x90, 0xF0,
+            0xF0, 0x10, 0x10, 0x10, 0x10,
+            0xF0, 0x90, 0xF0, 0x90, 0xF0,
+            0xF0, 0x90, 0xF0, 0x10, 0xF0,
+            0xF0, 0x90, 0xF0, 0x90, 0x90,
+            0xF0, 0x50, 0x70, 0x50, 0xF0,
+            0xF0, 0x80, 0x80, 0x80, 0xF0,
+            0xF0, 0x50, 0x50, 0x50, 0xF0,
+            0xF0, 0x80, 0xF0, 0x80, 0xF0,
+            0xF0, 0x80, 0xF0, 0x80, 0x80,
+        ],
+        big: Self::SCHIP_BIG,
+    };
+
+    /// ETI 660 interpreter (3 pixels wide)
+    pub const ETI_660: Font = Font {
+        small: [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0,
+            0x20, 0x20, 0x20, 0x20, 0x20,
+            0xE0, 0x20, 0xE0, 0x80, 0xE0,
+            0xE0, 0x20, 0xE0, 0x20, 0xE0,
+            0xA0, 0xA0, 0xE0, 0x20, 0x20,
+            0xE0, 0x80, 0xE0, 0x20, 0xE0,
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0,
+            0xE0, 0x20, 0x20, 0x20, 0x20,
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0,
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0,
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0,
+            0x80, 0x80, 0xE0, 0xA0, 0xE0,
+            0xE0, 0x80, 0x80, 0x80, 0xE0,
+            0x20, 0x20, 0xE0, 0xA0, 0xE0,
+            0xE0, 0x80, 0xE0, 0x80, 0xE0,
+            0xE0, 0x80, 0xC0, 0x80, 0x80,
+        ],
+        big: Self::SCHIP_BIG,
+    };
+
+    /// DREAM 6800 CHIPOS (3 pixels wide)
+    pub const DREAM_6800: Font = Font {
+        small: [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0,
+            0x40, 0x40, 0x40, 0x40, 0x40,
+            0xE0, 0x20, 0xE0, 0x80, 0xE0,
+            0xE0, 0x20, 0xE0, 0x20, 0xE0,
+            0x80, 0xA0, 0xA0, 0xE0, 0x20,
+            0xE0, 0x80, 0xE0, 0x20, 0xE0,
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0,
+            0xE0, 0x20, 0x20, 0x20, 0x20,
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0,
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0,
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0,
+            0xC0, 0xA0, 0xE0, 0xA0, 0xC0,
+            0xE0, 0x80, 0x80, 0x80, 0xE0,
+            0xC0, 0xA0, 0xA0, 0xA0, 0xC0,
+            0xE0, 0x80, 0xE0, 0x80, 0xE0,
+            0xE0, 0x80, 0xC0, 0x80, 0x80,
+        ],
+        big: Self::SCHIP_BIG,
+    };
+
+    /// Fish 'N' Chips interpreter
+    pub const FISH_N_CHIPS: Font = Font {
+        small: [
+            0x60, 0xA0, 0xA0, 0xA0, 0xC0,
+            0x40, 0xC0, 0x40, 0x40, 0xE0,
+            0xC0, 0x20, 0x40, 0x80, 0xE0,
+            0xC0, 0x20, 0x40, 0x20, 0xC0,
+            0x20, 0xA0, 0xE0, 0x20, 0x20,
+            0xE0, 0x80, 0xC0, 0x20, 0xC0,
+            0x40, 0x80, 0xC0, 0xA0, 0x40,
+            0xE0, 0x20, 0x60, 0x40, 0x40,
+            0x40, 0xA0, 0x40, 0xA0, 0x40,
+            0x40, 0xA0, 0x60, 0x20, 0x40,
+            0x40, 0xA0, 0xE0, 0xA0, 0xA0,
+            0xC0, 0xA0, 0xC0, 0xA0, 0xC0,
+            0x60, 0x80, 0x80, 0x80, 0x60,
+            0xC0, 0xA0, 0xA0, 0xA0, 0xC0,
+            0xE0, 0x80, 0xC0, 0x80, 0xE0,
+            0xE0, 0x80, 0xC0, 0x80, 0x80,
+        ],
+        big: Self::SCHIP_BIG,
+    };
+
+    /// Octo, whose small glyphs are the SUPER-CHIP ones most modern interpreters use
+    pub const OCTO: Font = Font {
+        small: [
+            0xF0, 0x90, 0x90, 0x90, 0xF0,
+            0x20, 0x60, 0x20, 0x20, 0x70,
+            0xF0, 0x10, 0xF0, 0x80, 0xF0,
+            0xF0, 0x10, 0xF0, 0x10, 0xF0,
+            0x90, 0x90, 0xF0, 0x10, 0x10,
+            0xF0, 0x80, 0xF0, 0x10, 0xF0,
+            0xF0, 0x80, 0xF0, 0x90, 0xF0,
+            0xF0, 0x10, 0x20, 0x40, 0x40,
+            0xF0, 0x90, 0xF0, 0x90, 0xF0,
+            0xF0, 0x90, 0xF0, 0x10, 0xF0,
+            0xF0, 0x90, 0xF0, 0x90, 0x90,
+            0xE0, 0x90, 0xE0, 0x90, 0xE0,
+            0xF0, 0x80, 0x80, 0x80, 0xF0,
+            0xE0, 0x90, 0x90, 0x90, 0xE0,
+            0xF0, 0x80, 0xF0, 0x80, 0xF0,
+            0xF0, 0x80, 0xF0, 0x80, 0x80,
+        ],
+        big: Self::SCHIP_BIG,
+    };
+
+    /// Reads a font binary: the 80 bytes of small glyphs, optionally followed by up to 160 bytes of large ones (the
+    /// SCHIP large digits fill in the rest)
+    pub fn from_binary(data: &[u8]) -> Result<Self, FontError> {
+        if data.len() < Self::SMALL_SIZE || data.len() > Self::SIZE {
+            return Err(FontError::Size(data.len()));
+        }
+
+        let mut font = Font { small: [0; Self::SMALL_SIZE], big: Self::SCHIP_BIG };
+
+        font.small.copy_from_slice(&data[..Self::SMALL_SIZE]);
+        font.big[..data.len() - Self::SMALL_SIZE].copy_from_slice(&data[Self::SMALL_SIZE..]);
+
+        Ok(font)
+    }
+
+    /// Small glyph of a hex digit
+    pub fn glyph(&self, digit: u8) -> &[u8] {
+        &self.small[Self::SMALL_GLYPH_SIZE * (digit as usize % Self::NUM_GLYPHS)..][..Self::SMALL_GLYPH_SIZE]
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::OCTO
+    }
+}
+
+impl FromStr for Font {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is = |names: &[&str]| names.iter().any(|name| s.eq_ignore_ascii_case(name));
+
+        if is(&["vip", "cosmac-vip"]) {
+            Ok(Font::VIP)
+        } else if is(&["eti660", "eti-660"]) {
+            Ok(Font::ETI_660)
+        } else if is(&["dream6800", "dream-6800"]) {
+            Ok(Font::DREAM_6800)
+        } else if is(&["fishnchips", "fish-n-chips", "fish"]) {
+            Ok(Font::FISH_N_CHIPS)
+        } else if is(&["octo", "schip"]) {
+            Ok(Font::OCTO)
+        } else {
+            Err(ConfigError::UnknownFont)
+        }
+    }
+}
+
+/// Invalid font binary
+#[derive(Debug)]
+pub enum FontError {
+    Size(usize),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Size(len) => write!(f, "font is {} bytes, expected {} to {}", len, Font::SMALL_SIZE, Font::SIZE),
+        }
+    }
+}
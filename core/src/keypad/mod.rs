@@ -0,0 +1,71 @@
+struct Keymap;
+
+impl Keymap {
+    pub fn cpu_index(key_index: usize) -> u8 {
+        const CPU_INDICES: [u8; Keypad::NUM] = [
+            0x1, 0x2, 0x3, 0xC,
+            0x4, 0x5, 0x6, 0xD,
+            0x7, 0x8, 0x9, 0xE,
+            0xA, 0x0, 0xB, 0xF,
+        ];
+
+        CPU_INDICES[key_index]
+    }
+
+    pub fn key_index(cpu_index: usize) -> usize {
+        const KEY_INDICES: [usize; Keypad::NUM] = [
+            0xD, 0x0, 0x1, 0x2,
+            0x4, 0x5, 0x6, 0x8,
+            0x9, 0xA, 0xC, 0xE,
+            0x3, 0x7, 0xB, 0xF,
+        ];
+
+        KEY_INDICES[cpu_index]
+    }
+}
+
+type KeyState = [bool; Keypad::NUM];
+
+pub struct Keypad {
+    state: KeyState,
+}
+
+impl Keypad {
+    /// Number of keys, laid out in a 4x4 grid (row-major key indices 0-15)
+    pub const NUM: usize = 16;
+
+    /// Returns the grid index of a Chip-8 key (0-F)
+    pub fn grid_index(cpu_index: usize) -> usize {
+        Keymap::key_index(cpu_index % Self::NUM)
+    }
+
+    pub fn is_key_pressed(&self, cpu_index: usize) -> bool {
+        self.state[Keymap::key_index(cpu_index % Self::NUM)]
+    }
+
+    /// Returns Some if any key is pressed (key with the lowest index is returned)
+    pub fn any_key(&self) -> Option<u8> {
+        for key_index in 0..Self::NUM {
+            if self.state[key_index] {
+                return Some(Keymap::cpu_index(key_index));
+            }
+        }
+    
+        None
+    }
+
+    /// Fills key state from the grid indices of the currently pressed keys
+    pub fn update_state<I: IntoIterator<Item = usize>>(&mut self, key_indices: I) {
+        self.state.fill(false);
+
+        key_indices.into_iter().filter(|&key_index| key_index < Self::NUM).for_each(|key_index| {
+            self.state[key_index] = true;
+        });
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self { state: [false; Self::NUM] }
+    }
+}
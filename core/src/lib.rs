@@ -0,0 +1,397 @@
+#![no_std]
+
+extern crate alloc;
+
+use crate::{
+    bus::{Bus, BusFault, OnViolation, Violation, memory::Memory},
+    config::{Config, Variant},
+    cpu::{Cpu, CpuEvent, chip8x::IoPort, ext::CustomOpcode, lint::QuirkLinter, profile::Profiler, regfile::RegFile, vipmap},
+    display::Display,
+    font::Font,
+    keypad::Keypad,
+};
+
+use alloc::vec::Vec;
+
+pub mod bus;
+pub mod cdp1802;
+pub mod config;
+pub mod cpu;
+pub mod disasm;
+pub mod display;
+pub mod flow;
+pub mod font;
+pub mod keypad;
+pub mod loader;
+pub mod vip;
+
+/// Chip-8 machine (CPU, memory, display and keypad)
+pub struct Core {
+    cpu: Cpu,
+    config: Config,
+
+    /// Bus fault that ended the last frame early
+    fault: Option<BusFault>,
+
+    /// Size of the loaded program in bytes
+    program_len: usize,
+}
+
+impl Core {
+    /// Default font address
+    pub const FONT_START: usize = 0x50;
+    pub const ROM_START: usize = 0x200;
+
+    #[deprecated(note = "use `Core::FONT_START`")]
+    pub const SPRITES_START: usize = Self::FONT_START;
+    #[deprecated(note = "use `Font::SMALL_GLYPH_SIZE`")]
+    pub const SPRITE_SIZE: usize = Font::SMALL_GLYPH_SIZE;
+    #[deprecated(note = "use `Font::NUM_GLYPHS`")]
+    pub const NUM_SPRITES: usize = Font::NUM_GLYPHS;
+    #[deprecated(note = "use `Font::SMALL_SIZE`")]
+    pub const SPRITES_SIZE: usize = Font::SMALL_SIZE;
+
+    pub const MAX_ROM_SIZE: usize = Memory::SIZE - Self::ROM_START;
+
+    /// First instruction of CHIP-8 HIRES programs, a jump to the 64x64 display setup bundled with the program
+    pub const HIRES_SIGNATURE: [u8; 2] = [0x12, 0x60];
+
+    /// Start of the game proper in CHIP-8 HIRES programs
+    const HIRES_START: u16 = 0x2C0;
+
+    /// Creates a machine with the given ROM loaded (ROMs larger than `Config::max_rom_size` are truncated)
+    pub fn new(rom: &[u8], seed: u64) -> Self {
+        Self::with_config(rom, seed, Config::default())
+    }
+
+    /// Creates a machine with the given ROM loaded, switching to `Variant::Hires` if it starts with `HIRES_SIGNATURE` and
+    /// no variant was set (see `Config::detect_variant`)
+    pub fn with_config(rom: &[u8], seed: u64, mut config: Config) -> Self {
+        config.detect_variant(rom);
+
+        let mut mem = Memory::new(config.variant.memory_size());
+
+        // Configurations that skipped `Config::validate` only get the part of the font that fits in memory
+        let font = config.font_range();
+        let (start, end) = (usize::min(font.start, mem.len()), usize::min(font.end, mem.len()));
+        let glyphs = config.font.small.iter().chain(&config.font.big);
+
+        for (byte, glyph) in mem[start..end].iter_mut().zip(glyphs) {
+            *byte = *glyph;
+        }
+
+        let mut core = Self {
+            cpu: Cpu::new(Bus::new(mem), seed, config.quirks, config.variant, config.vip_memory_map, config.font_address, config.rpl_flags),
+            config,
+            fault: None,
+            program_len: 0,
+        };
+
+        core.load_rom(rom);
+        core.cpu.reset(core.config.entry_point);
+        core
+    }
+
+    /// Copies a ROM to the load address, returning the number of bytes loaded
+    fn load_rom(&mut self, rom: &[u8]) -> usize {
+        let start = usize::min(self.config.load_address as usize, self.config.variant.memory_size());
+        let len = usize::min(rom.len(), self.config.max_rom_size());
+
+        self.program_len = len;
+        self.cpu.bus_mut().mark_initialized(start..start + len);
+
+        let mem = self.cpu.bus_mut().memory_mut();
+
+        mem[start..start + len].copy_from_slice(&rom[..len]);
+
+        // The bundled setup code drives the VIP's 1861 directly, so skip it and run the game on the emulated 64x64 display
+        if self.config.variant == Variant::Hires && mem[start..start + len].starts_with(&Self::HIRES_SIGNATURE) {
+            mem[start..start + 2].copy_from_slice(&(0x1000 | Self::HIRES_START).to_be_bytes());
+        }
+
+        len
+    }
+
+    /// Replaces the program with a new ROM image
+    ///
+    /// With `keep_state`, only the program bytes are patched and execution continues where it left off. Otherwise
+    /// the rest of the program area is cleared and the registers, stack and display are reset.
+    pub fn reload(&mut self, rom: &[u8], keep_state: bool) {
+        let len = self.load_rom(rom);
+
+        if !keep_state {
+            let size = self.config.variant.memory_size();
+            let end = usize::min(self.config.load_address as usize + len, size);
+
+            self.cpu.bus_mut().memory_mut()[end..size].fill(0);
+            self.cpu.reset(self.config.entry_point);
+        }
+    }
+
+    /// Runs one 60 Hz frame: ticks the timers, then steps the CPU until it draws, waits for a key or runs out of steps
+    ///
+    /// The bus access log is reset at the start of every frame, so afterwards it holds the accesses of this frame. A bus
+    /// fault also ends the frame; see `take_fault`.
+    pub fn frame(&mut self) {
+        self.cpu.bus_mut().access_log_mut().clear();
+        self.cpu.tick();
+
+        'step_cpu: for _ in 0..self.config.steps {
+            if let Some(event) = self.cpu.step() {
+                match event {
+                    CpuEvent::Draw if self.config.quirks.vblank => break 'step_cpu,
+                    CpuEvent::Draw => {}
+                    CpuEvent::WaitForKey | CpuEvent::WaitForInput => break 'step_cpu,
+                    CpuEvent::Fault(fault) => {
+                        self.fault = Some(fault);
+
+                        break 'step_cpu;
+                    }
+                }
+            }
+        }
+
+        self.cpu.sync_display();
+    }
+
+    /// Reports accesses past the end of memory, reads of bytes never written and writes below the program area
+    /// (see `Bus::enable_strict`)
+    ///
+    /// The font, the program and, with the VIP memory map, the stack and display count as written.
+    pub fn enable_strict(&mut self, on_violation: OnViolation) {
+        let reserved = 0..u32::min(self.config.load_address as u32, Self::ROM_START as u32);
+        let program = self.config.load_address as usize..self.config.load_address as usize + self.program_len;
+        let vip_memory_map = self.config.vip_memory_map;
+        let font = self.config.font_range();
+
+        let bus = self.cpu.bus_mut();
+
+        bus.enable_strict(on_violation, reserved);
+        bus.mark_initialized(font);
+        bus.mark_initialized(program);
+
+        if vip_memory_map {
+            bus.mark_initialized(vipmap::VIP_STACK as usize..Memory::SIZE);
+        }
+    }
+
+    /// Returns and clears the accesses strict mode logged
+    pub fn take_violations(&mut self) -> Vec<Violation> {
+        self.cpu.bus_mut().take_violations()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Adds a custom instruction (see `cpu::ext`); instructions registered later take precedence
+    pub fn register_opcode(&mut self, opcode: CustomOpcode) {
+        self.cpu.register_opcode(opcode);
+    }
+
+    /// Renders the display into a 0RGB buffer of `display().width() * display().height()` pixels
+    ///
+    /// CHIP-8X uses the color zones and background color, MegaChip mode the last presented frame faded by the screen
+    /// alpha, everything else the configured palette.
+    pub fn render(&self, out: &mut [u32]) {
+        let display = self.display();
+        let megachip = self.cpu.megachip();
+
+        if megachip.is_enabled() {
+            let alpha = megachip.alpha() as u32;
+
+            for (out, pixel) in out.iter_mut().zip(display.front()) {
+                *out = (0..3).map(|i| 8 * i).fold(0, |rgb, shift| rgb | (((pixel >> shift) & 0xFF) * alpha / 0xFF) << shift);
+            }
+
+            return;
+        }
+
+        for (index, (out, pixel)) in out.iter_mut().zip(display.as_slice()).enumerate() {
+            let (x, y) = (index % display.width(), index / display.width());
+
+            *out = match (self.config.variant, *pixel != 0) {
+                (Variant::Chip8X, true) => Display::FOREGROUND_COLORS[display.zone_color(x, y) as usize],
+                (Variant::Chip8X, false) => Display::BACKGROUND_COLORS[display.background() as usize],
+                (_, lit) => self.config.palette[lit as usize],
+            };
+        }
+    }
+
+    /// Starts profiling (see `cpu::profile`)
+    pub fn enable_profiler(&mut self) {
+        self.cpu.enable_profiler();
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.cpu.profiler()
+    }
+
+    /// Starts checking for quirk-dependent behaviour (see `cpu::lint`)
+    pub fn enable_quirk_linter(&mut self) {
+        self.cpu.enable_quirk_linter();
+    }
+
+    pub fn quirk_linter(&self) -> Option<&QuirkLinter> {
+        self.cpu.quirk_linter()
+    }
+
+    pub fn display(&self) -> &Display {
+        self.cpu.display()
+    }
+
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        self.cpu.keypad_mut()
+    }
+
+    /// Returns the second (CHIP-8X) keypad
+    pub fn second_keypad_mut(&mut self) -> &mut Keypad {
+        self.cpu.second_keypad_mut()
+    }
+
+    pub fn port(&self) -> &IoPort {
+        self.cpu.port()
+    }
+
+    pub fn port_mut(&mut self) -> &mut IoPort {
+        self.cpu.port_mut()
+    }
+
+    pub fn bus(&self) -> &Bus {
+        self.cpu.bus()
+    }
+
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        self.cpu.bus_mut()
+    }
+
+    pub fn regfile(&self) -> &RegFile {
+        self.cpu.regfile()
+    }
+
+    /// RPL user flags saved by Fx75, for keeping them between sessions
+    pub fn user_flags(&self) -> &[u8] {
+        self.cpu.user_flags()
+    }
+
+    pub fn user_flags_mut(&mut self) -> &mut [u8] {
+        self.cpu.user_flags_mut()
+    }
+
+    pub fn is_sound_active(&self) -> bool {
+        self.cpu.is_sound_active()
+    }
+
+    /// Returns and clears the bus fault that stopped the last frame, if any
+    pub fn take_fault(&mut self) -> Option<BusFault> {
+        self.fault.take()
+    }
+
+    /// Mixes the MegaChip digitised sound of the last frame into `out`, resampled to `sample_rate`
+    pub fn mix_digital_sound(&self, out: &mut [i16], sample_rate: u32) {
+        self.cpu.megachip().mix_sound(self.bus().memory(), out, sample_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bus::{Access, FaultKind};
+
+    #[test]
+    fn font_past_the_end_of_memory() {
+        let mut config = Config { font_address: 0xF80, ..Config::default() };
+
+        assert!(config.validate(2).is_err());
+
+        let core = Core::with_config(&[0x12, 0x00], 0, config);
+        let mem = core.bus().memory();
+
+        assert_eq!(mem[0xF80..0xFD0], Font::OCTO.small);
+        assert_eq!(mem[0xFD0..0x1000], Font::OCTO.big[..0x30]);
+
+        config.font_address = 0xFFFF;
+
+        Core::with_config(&[0x12, 0x00], 0, config);
+    }
+
+    #[test]
+    fn hires_detection() {
+        let rom = [0x12, 0x60];
+
+        assert_eq!(Core::new(&rom, 0).config().variant, Variant::Hires);
+
+        let mut config = Config::default();
+
+        config.set_variant(Variant::Chip8);
+
+        assert_eq!(Core::with_config(&rom, 0, config).config().variant, Variant::Chip8);
+    }
+
+    #[test]
+    fn big_font() {
+        let config = Config { font_address: 0x100, ..Config::default() };
+
+        // V0 = 2, I = big digit V0
+        let mut core = Core::with_config(&[0x60, 0x02, 0xF0, 0x30, 0x12, 0x04], 0, config);
+
+        core.frame();
+
+        let addr = core.regfile().index as usize;
+
+        assert_eq!(addr, 0x100 + Font::SMALL_SIZE + 2 * Font::BIG_GLYPH_SIZE);
+        assert_eq!(core.bus().memory()[addr..addr + Font::BIG_GLYPH_SIZE], Font::SCHIP_BIG[20..30]);
+    }
+
+    /// Runs a frame in strict mode and returns the fault that stopped it
+    fn strict_fault(rom: &[u8]) -> Option<BusFault> {
+        let mut core = Core::new(rom, 0);
+
+        core.enable_strict(OnViolation::Fault);
+        core.frame();
+
+        core.take_fault()
+    }
+
+    #[test]
+    fn strict_out_of_range() {
+        // I = FFF, V0 = 2, I += V0, load V0 from 1001
+        let fault = strict_fault(&[0xAF, 0xFF, 0x60, 0x02, 0xF0, 0x1E, 0xF0, 0x65, 0x12, 0x08]);
+
+        assert_eq!(fault, Some(BusFault { kind: FaultKind::OutOfRange, addr: 0x1001, access: Access::Read }));
+    }
+
+    #[test]
+    fn strict_uninitialized() {
+        // Load V0 from 300, past the end of the program
+        let fault = strict_fault(&[0xA3, 0x00, 0xF0, 0x65, 0x12, 0x04]);
+
+        assert_eq!(fault, Some(BusFault { kind: FaultKind::Uninitialized, addr: 0x300, access: Access::Read }));
+    }
+
+    #[test]
+    fn strict_reserved() {
+        // Store V0 at 100, below the program
+        let fault = strict_fault(&[0xA1, 0x00, 0xF0, 0x55, 0x12, 0x04]);
+
+        assert_eq!(fault, Some(BusFault { kind: FaultKind::Reserved, addr: 0x100, access: Access::Write(0) }));
+    }
+
+    #[test]
+    fn strict_log() {
+        let mut core = Core::new(&[0xA3, 0x00, 0xF0, 0x65, 0x12, 0x04], 0);
+
+        core.enable_strict(OnViolation::Log);
+        core.frame();
+
+        let fault = BusFault { kind: FaultKind::Uninitialized, addr: 0x300, access: Access::Read };
+
+        assert_eq!(core.take_fault(), None);
+        assert_eq!(core.take_violations(), [Violation { at: 0x202, fault }]);
+
+        // The frame ran on to the final jump
+        assert_eq!(core.regfile().pc, 0x204);
+    }
+
+}
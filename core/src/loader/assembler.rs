@@ -0,0 +1,1062 @@
+use crate::loader::LoadError;
+
+use alloc::{collections::{BTreeMap, VecDeque}, format, string::{String, ToString}, vec::Vec};
+use core::convert::TryFrom;
+
+/// Address the assembled program starts at
+const ORIGIN: u32 = 0x200;
+
+/// Macro expansions allowed before a program is considered recursive
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// How a label address is written into the program once it is known
+#[derive(Clone, Copy)]
+enum Patch {
+    /// Low 12 bits of an instruction (`jump`, `i :=`, calls)
+    Nnn,
+
+    /// 16-bit big-endian address (`i := long`, `:pointer`)
+    Long,
+
+    /// `:unpack` high byte: the nibble, then bits 8-11 of the address
+    UnpackHi(u8),
+
+    /// `:unpack long` high byte
+    LongHi,
+
+    /// `:unpack` low byte
+    Lo,
+}
+
+struct Fixup {
+    at: u32,
+    patch: Patch,
+    label: String,
+    line: usize,
+}
+
+/// Operand of a comparison
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+/// Condition of `if` and `while`: a register compared with a register or a byte, or a key test
+struct Condition {
+    x: u8,
+    op: String,
+    operand: Option<Operand>,
+}
+
+/// Octo assembler
+///
+/// Covers the Octo language as far as it maps onto instructions: labels, `:const`, `:alias`, `:unpack`, `:next`,
+/// `:org`, `:byte`, `:pointer`, `:call`, `:macro`, `:calc`, `:assert`, the structured `if`/`else`/`end` and
+/// `loop`/`while`/`again` blocks and every CHIP-8, SUPER-CHIP and XO-CHIP statement. `:calc` uses integer arithmetic,
+/// and `:stringmode` is not supported.
+struct Assembler {
+    tokens: VecDeque<Token>,
+    line: usize,
+
+    /// Bytes from 0x200 on
+    rom: Vec<u8>,
+    here: u32,
+
+    labels: BTreeMap<String, u32>,
+    constants: BTreeMap<String, i64>,
+    aliases: BTreeMap<String, u8>,
+    macros: BTreeMap<String, Macro>,
+    expansions: usize,
+
+    fixups: Vec<Fixup>,
+
+    /// Address of the jump to patch at the `else` or `end` of each open `begin` block
+    ifs: Vec<u32>,
+
+    /// Start and `while` jumps of each open loop
+    loops: Vec<(u32, Vec<u32>)>,
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let mut rest = line;
+
+        loop {
+            rest = rest.trim_start();
+
+            if rest.is_empty() || rest.starts_with('#') {
+                break;
+            }
+
+            // Strings (`:assert` messages) are a single token
+            let end = if let Some(string) = rest.strip_prefix('"') {
+                string.find('"').map_or(rest.len(), |end| end + 2)
+            } else {
+                rest.find(char::is_whitespace).unwrap_or(rest.len())
+            };
+
+            tokens.push_back(Token { text: rest[..end].to_string(), line: index + 1 });
+            rest = &rest[end..];
+        }
+    }
+
+    tokens
+}
+
+/// Parses a decimal, `0x` hexadecimal or `0b` binary literal, optionally negative
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }?;
+
+    Some(if negative { -value } else { value })
+}
+
+/// Returns the register named `v0`-`vf` (either case)
+fn register(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|n| n as u8),
+        _ => None,
+    }
+}
+
+impl Assembler {
+    fn new(source: &str) -> Self {
+        let aliases = [("compare-temp", 0xF), ("unpack-hi", 0x0), ("unpack-lo", 0x1)]
+            .iter()
+            .map(|&(name, reg)| (name.to_string(), reg))
+            .collect();
+
+        Self {
+            tokens: tokenize(source),
+            line: 0,
+            rom: Vec::new(),
+            here: ORIGIN,
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            aliases,
+            macros: BTreeMap::new(),
+            expansions: 0,
+            fixups: Vec::new(),
+            ifs: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> LoadError {
+        LoadError::OctoSource { line: self.line, reason: reason.into() }
+    }
+
+    fn next(&mut self) -> Result<String, LoadError> {
+        let token = self.tokens.pop_front().ok_or_else(|| self.error("unexpected end of source"))?;
+
+        self.line = token.line;
+
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), LoadError> {
+        let token = self.next()?;
+
+        if token == expected {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`, found `{}`", expected, token)))
+        }
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), LoadError> {
+        let index = self.here.checked_sub(ORIGIN).ok_or_else(|| self.error("program below 0x200"))? as usize;
+
+        if self.here > 0xFFFF {
+            return Err(self.error("program exceeds 64 KiB"));
+        }
+
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+        }
+
+        self.rom[index] = byte;
+        self.here += 1;
+
+        Ok(())
+    }
+
+    fn emit_op(&mut self, opcode: u16) -> Result<(), LoadError> {
+        let [hi, lo] = opcode.to_be_bytes();
+
+        self.emit(hi)?;
+        self.emit(lo)
+    }
+
+    fn patch(&mut self, at: u32, patch: Patch, addr: u32) {
+        let index = (at - ORIGIN) as usize;
+
+        match patch {
+            Patch::Nnn => {
+                self.rom[index] = (self.rom[index] & 0xF0) | ((addr >> 8) & 0xF) as u8;
+                self.rom[index + 1] = addr as u8;
+            }
+            Patch::Long => {
+                self.rom[index] = (addr >> 8) as u8;
+                self.rom[index + 1] = addr as u8;
+            }
+            Patch::UnpackHi(nibble) => self.rom[index] = (nibble << 4) | ((addr >> 8) & 0xF) as u8,
+            Patch::LongHi => self.rom[index] = (addr >> 8) as u8,
+            Patch::Lo => self.rom[index] = addr as u8,
+        }
+    }
+
+    /// Writes the address of `label` at `at`, now if it is defined, once it is otherwise
+    fn reference(&mut self, at: u32, patch: Patch, label: String) {
+        match self.labels.get(&label) {
+            Some(&addr) => self.patch(at, patch, addr),
+            None => self.fixups.push(Fixup { at, patch, label, line: self.line }),
+        }
+    }
+
+    /// Emits `opcode` with the address of a label or a literal in its low 12 bits
+    fn emit_nnn(&mut self, opcode: u16) -> Result<(), LoadError> {
+        let target = self.next()?;
+        let at = self.here;
+
+        self.emit_op(opcode)?;
+
+        match self.known(&target) {
+            Some(addr) if (0..=0xFFF).contains(&addr) => self.patch(at, Patch::Nnn, addr as u32),
+            Some(addr) => return Err(self.error(format!("address {:#X} does not fit in 12 bits", addr))),
+            None => self.reference(at, Patch::Nnn, self.identifier(target)?),
+        }
+
+        Ok(())
+    }
+
+    fn identifier(&self, name: String) -> Result<String, LoadError> {
+        if register(&name).is_some() || name.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == ':') {
+            Err(self.error(format!("`{}` is not a valid name", name)))
+        } else {
+            Ok(name)
+        }
+    }
+
+    /// Value of a literal, constant or already defined label
+    fn known(&self, text: &str) -> Option<i64> {
+        number(text)
+            .or_else(|| self.constants.get(text).copied())
+            .or_else(|| self.labels.get(text).map(|&addr| i64::from(addr)))
+    }
+
+    fn value(&mut self, min: i64, max: i64) -> Result<i64, LoadError> {
+        let text = self.next()?;
+
+        let value = if text == "{" {
+            self.calc()?
+        } else {
+            self.known(&text).ok_or_else(|| self.error(format!("undefined name `{}`", text)))?
+        };
+
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(self.error(format!("value {} out of range", value)))
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        Ok(self.value(-128, 255)? as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8, LoadError> {
+        Ok(self.value(0, 15)? as u8)
+    }
+
+    fn register(&mut self) -> Result<u8, LoadError> {
+        let text = self.next()?;
+
+        self.to_register(&text).ok_or_else(|| self.error(format!("expected a register, found `{}`", text)))
+    }
+
+    fn to_register(&self, text: &str) -> Option<u8> {
+        register(text).or_else(|| self.aliases.get(text).copied())
+    }
+
+    /// Evaluates a `:calc` expression up to the closing brace
+    fn calc(&mut self) -> Result<i64, LoadError> {
+        let value = self.expression()?;
+
+        self.expect("}")?;
+
+        Ok(value)
+    }
+
+    /// Octo evaluates binary operators right to left, without precedence
+    fn expression(&mut self) -> Result<i64, LoadError> {
+        let left = self.term()?;
+
+        let op = match self.peek() {
+            Some("}") | Some(")") | None => return Ok(left),
+            Some(_) => self.next()?,
+        };
+
+        let right = self.expression()?;
+
+        let value = match op.as_str() {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            "&" => Some(left & right),
+            "|" => Some(left | right),
+            "^" => Some(left ^ right),
+            "<<" => u32::try_from(right).ok().and_then(|n| left.checked_shl(n)),
+            ">>" => u32::try_from(right).ok().and_then(|n| left.checked_shr(n)),
+            "pow" => u32::try_from(right).ok().and_then(|n| left.checked_pow(n)),
+            "min" => Some(left.min(right)),
+            "max" => Some(left.max(right)),
+            "<" => Some((left < right) as i64),
+            ">" => Some((left > right) as i64),
+            "<=" => Some((left <= right) as i64),
+            ">=" => Some((left >= right) as i64),
+            "==" => Some((left == right) as i64),
+            "!=" => Some((left != right) as i64),
+            _ => return Err(self.error(format!("unknown operator `{}`", op))),
+        };
+
+        value.ok_or_else(|| self.error(format!("`{} {} {}` cannot be computed", left, op, right)))
+    }
+
+    fn term(&mut self) -> Result<i64, LoadError> {
+        let text = self.next()?;
+
+        match text.as_str() {
+            "(" => {
+                let value = self.expression()?;
+
+                self.expect(")")?;
+
+                Ok(value)
+            }
+            "-" => Ok(-self.term()?),
+            "~" => Ok(!self.term()?),
+            "!" => Ok((self.term()? == 0) as i64),
+            "abs" => Ok(self.term()?.abs()),
+            "sign" => Ok(self.term()?.signum()),
+            "floor" | "ceil" => self.term(),
+            "HERE" => Ok(i64::from(self.here)),
+            _ => self.known(&text).ok_or_else(|| self.error(format!("undefined name `{}` in expression", text))),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, LoadError> {
+        let x = self.register()?;
+        let op = self.next()?;
+
+        let operand = match op.as_str() {
+            "key" | "-key" => None,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                let text = self.peek().unwrap_or("").to_string();
+
+                Some(match self.to_register(&text) {
+                    Some(y) => {
+                        self.next()?;
+
+                        Operand::Register(y)
+                    }
+                    None => Operand::Byte(self.byte()?),
+                })
+            }
+            _ => return Err(self.error(format!("unknown comparison `{}`", op))),
+        };
+
+        Ok(Condition { x, op, operand })
+    }
+
+    /// Emits a skip over the next instruction when `condition` is false, or when it is true if `negate` is set
+    fn emit_condition(&mut self, condition: &Condition, negate: bool) -> Result<(), LoadError> {
+        let x = u16::from(condition.x);
+
+        // Skip opcodes for == and !=, register and immediate forms
+        let skip = |equal: bool, operand: &Operand| match (equal, operand) {
+            (true, Operand::Byte(n)) => 0x4000 | x << 8 | u16::from(*n),
+            (false, Operand::Byte(n)) => 0x3000 | x << 8 | u16::from(*n),
+            (true, Operand::Register(y)) => 0x9000 | x << 8 | u16::from(*y) << 4,
+            (false, Operand::Register(y)) => 0x5000 | x << 8 | u16::from(*y) << 4,
+        };
+
+        let op = match (condition.op.as_str(), negate) {
+            ("<", true) => ">=",
+            (">", true) => "<=",
+            ("<=", true) => ">",
+            (">=", true) => "<",
+            ("==", true) => "!=",
+            ("!=", true) => "==",
+            ("key", true) => "-key",
+            ("-key", true) => "key",
+            (op, _) => op,
+        };
+
+        match (op, &condition.operand) {
+            ("key", _) => self.emit_op(0xE0A1 | x << 8),
+            ("-key", _) => self.emit_op(0xE09E | x << 8),
+            ("==", Some(operand)) => self.emit_op(skip(true, operand)),
+            ("!=", Some(operand)) => self.emit_op(skip(false, operand)),
+            (_, Some(operand)) => {
+                // vf := y (or the byte), then subtract in the order that leaves the comparison in the borrow flag
+                match operand {
+                    Operand::Register(y) => self.emit_op(0x8F00 | u16::from(*y) << 4)?,
+                    Operand::Byte(n) => self.emit_op(0x6F00 | u16::from(*n))?,
+                }
+
+                match op {
+                    ">" => { self.emit_op(0x8F05 | x << 4)?; self.emit_op(0x3F01) }
+                    "<" => { self.emit_op(0x8F07 | x << 4)?; self.emit_op(0x3F01) }
+                    ">=" => { self.emit_op(0x8F07 | x << 4)?; self.emit_op(0x4F01) }
+                    _ => { self.emit_op(0x8F05 | x << 4)?; self.emit_op(0x4F01) }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Emits a `jump` whose target is patched when its block ends
+    fn emit_placeholder(&mut self) -> Result<u32, LoadError> {
+        let at = self.here;
+
+        self.emit_op(0x1000)?;
+
+        Ok(at)
+    }
+
+    fn assign(&mut self, x: u8) -> Result<(), LoadError> {
+        let x16 = u16::from(x) << 8;
+        let op = self.next()?;
+
+        let source = self.peek().unwrap_or("").to_string();
+        let y = self.to_register(&source);
+
+        if y.is_some() {
+            self.next()?;
+        }
+
+        let xy = |n: u16| 0x8000 | x16 | u16::from(y.unwrap_or(0)) << 4 | n;
+
+        match (op.as_str(), y) {
+            (":=", Some(_)) => self.emit_op(xy(0x0)),
+            ("|=", Some(_)) => self.emit_op(xy(0x1)),
+            ("&=", Some(_)) => self.emit_op(xy(0x2)),
+            ("^=", Some(_)) => self.emit_op(xy(0x3)),
+            ("+=", Some(_)) => self.emit_op(xy(0x4)),
+            ("-=", Some(_)) => self.emit_op(xy(0x5)),
+            (">>=", Some(_)) => self.emit_op(xy(0x6)),
+            ("=-", Some(_)) => self.emit_op(xy(0x7)),
+            ("<<=", Some(_)) => self.emit_op(xy(0xE)),
+            (":=", None) => match source.as_str() {
+                "random" => {
+                    self.next()?;
+
+                    let mask = self.byte()?;
+
+                    self.emit_op(0xC000 | x16 | u16::from(mask))
+                }
+                "delay" => {
+                    self.next()?;
+                    self.emit_op(0xF007 | x16)
+                }
+                "key" => {
+                    self.next()?;
+                    self.emit_op(0xF00A | x16)
+                }
+                _ => {
+                    let n = self.byte()?;
+
+                    self.emit_op(0x6000 | x16 | u16::from(n))
+                }
+            },
+            ("+=", None) => {
+                let n = self.byte()?;
+
+                self.emit_op(0x7000 | x16 | u16::from(n))
+            }
+            ("-=", None) => {
+                let n = self.byte()?;
+
+                self.emit_op(0x7000 | x16 | u16::from(n.wrapping_neg()))
+            }
+            _ => Err(self.error(format!("unknown operation `v{:X} {} {}`", x, op, source))),
+        }
+    }
+
+    fn assign_i(&mut self) -> Result<(), LoadError> {
+        let op = self.next()?;
+
+        match op.as_str() {
+            "+=" => {
+                let x = u16::from(self.register()?);
+
+                self.emit_op(0xF01E | x << 8)
+            }
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+
+                    let x = u16::from(self.register()?);
+
+                    self.emit_op(0xF029 | x << 8)
+                }
+                Some("bighex") => {
+                    self.next()?;
+
+                    let x = u16::from(self.register()?);
+
+                    self.emit_op(0xF030 | x << 8)
+                }
+                Some("long") => {
+                    self.next()?;
+
+                    let target = self.next()?;
+
+                    self.emit_op(0xF000)?;
+
+                    let at = self.here;
+
+                    self.emit_op(0)?;
+
+                    match self.known(&target) {
+                        Some(addr) if (0..=0xFFFF).contains(&addr) => self.patch(at, Patch::Long, addr as u32),
+                        Some(addr) => return Err(self.error(format!("address {:#X} does not fit in 16 bits", addr))),
+                        None => self.reference(at, Patch::Long, self.identifier(target)?),
+                    }
+
+                    Ok(())
+                }
+                _ => self.emit_nnn(0xA000),
+            },
+            _ => Err(self.error(format!("unknown operation `i {}`", op))),
+        }
+    }
+
+    /// `save vx`, `save vx - vy` and the same for `load`
+    fn memory(&mut self, single: u16, range: u16) -> Result<(), LoadError> {
+        let x = u16::from(self.register()?);
+
+        if self.peek() == Some("-") {
+            self.next()?;
+
+            let y = u16::from(self.register()?);
+
+            self.emit_op(range | x << 8 | y << 4)
+        } else {
+            self.emit_op(single | x << 8)
+        }
+    }
+
+    fn define_label(&mut self, name: String, addr: u32) -> Result<(), LoadError> {
+        let name = self.identifier(name)?;
+
+        if self.labels.contains_key(&name) {
+            return Err(self.error(format!("label `{}` is defined twice", name)));
+        }
+
+        self.labels.insert(name, addr);
+
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), LoadError> {
+        let name = self.next()?;
+        let mut params = Vec::new();
+
+        loop {
+            let param = self.next()?;
+
+            if param == "{" {
+                break;
+            }
+
+            params.push(param);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+
+        loop {
+            let token = self.tokens.pop_front().ok_or_else(|| self.error(format!("unterminated macro `{}`", name)))?;
+
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+
+            if depth == 0 {
+                break;
+            }
+
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { params, body });
+
+        Ok(())
+    }
+
+    fn expand(&mut self, name: &str) -> Result<(), LoadError> {
+        self.expansions += 1;
+
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(self.error(format!("macro `{}` expands without end", name)));
+        }
+
+        let count = self.macros[name].params.len();
+        let mut args = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            args.push(self.next()?);
+        }
+
+        let line = self.line;
+        let definition = &self.macros[name];
+
+        for token in definition.body.iter().rev() {
+            let text = match definition.params.iter().position(|param| *param == token.text) {
+                Some(index) => args[index].clone(),
+                None => token.text.clone(),
+            };
+
+            // Errors inside the expansion point at the invocation
+            self.tokens.push_front(Token { text, line });
+        }
+
+        Ok(())
+    }
+
+    fn directive(&mut self, token: &str) -> Result<(), LoadError> {
+        match token {
+            ":" => {
+                let name = self.next()?;
+
+                self.define_label(name, self.here)
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let name = self.identifier(name)?;
+                let reg = if self.peek() == Some("{") {
+                    self.next()?;
+
+                    let value = self.calc()?;
+
+                    u8::try_from(value).ok().filter(|&n| n < 16).ok_or_else(|| self.error("alias is not a register"))?
+                } else {
+                    self.register()?
+                };
+
+                self.aliases.insert(name, reg);
+
+                Ok(())
+            }
+            ":const" => {
+                let name = self.next()?;
+                let name = self.identifier(name)?;
+                let value = self.value(i64::MIN, i64::MAX)?;
+
+                self.constants.insert(name, value);
+
+                Ok(())
+            }
+            ":calc" => {
+                let name = self.next()?;
+                let name = self.identifier(name)?;
+
+                self.expect("{")?;
+
+                let value = self.calc()?;
+
+                self.constants.insert(name, value);
+
+                Ok(())
+            }
+            ":unpack" => {
+                let long = self.peek() == Some("long");
+
+                if long {
+                    self.next()?;
+                }
+
+                let nibble = if long { 0 } else { self.nibble()? };
+                let target = self.next()?;
+                let (hi, lo) = (self.aliases["unpack-hi"], self.aliases["unpack-lo"]);
+
+                let hi_at = self.here + 1;
+
+                self.emit_op(0x6000 | u16::from(hi) << 8)?;
+
+                let lo_at = self.here + 1;
+
+                self.emit_op(0x6000 | u16::from(lo) << 8)?;
+
+                let hi_patch = if long { Patch::LongHi } else { Patch::UnpackHi(nibble) };
+
+                match self.known(&target) {
+                    Some(addr) => {
+                        self.patch(hi_at, hi_patch, addr as u32);
+                        self.patch(lo_at, Patch::Lo, addr as u32);
+                    }
+                    None => {
+                        let label = self.identifier(target)?;
+
+                        self.reference(hi_at, hi_patch, label.clone());
+                        self.reference(lo_at, Patch::Lo, label);
+                    }
+                }
+
+                Ok(())
+            }
+            ":next" => {
+                let name = self.next()?;
+
+                self.define_label(name, self.here + 1)
+            }
+            ":org" => {
+                let addr = self.value(i64::from(ORIGIN), 0xFFFF)?;
+
+                self.here = addr as u32;
+
+                Ok(())
+            }
+            ":byte" => {
+                let n = self.byte()?;
+
+                self.emit(n)
+            }
+            ":pointer" => {
+                let target = self.next()?;
+                let at = self.here;
+
+                self.emit_op(0)?;
+
+                match self.known(&target) {
+                    Some(addr) => self.patch(at, Patch::Long, addr as u32),
+                    None => self.reference(at, Patch::Long, self.identifier(target)?),
+                }
+
+                Ok(())
+            }
+            ":call" => self.emit_nnn(0x2000),
+            ":macro" => self.define_macro(),
+            ":assert" => {
+                if self.peek().is_some_and(|text| text.starts_with('"')) {
+                    self.next()?;
+                }
+
+                self.expect("{")?;
+
+                if self.calc()? == 0 {
+                    Err(self.error("assertion failed"))
+                } else {
+                    Ok(())
+                }
+            }
+            // Debugger annotations
+            ":breakpoint" => self.next().map(drop),
+            ":monitor" => {
+                self.next()?;
+                self.next().map(drop)
+            }
+            ":stringmode" => Err(self.error("`:stringmode` is not supported")),
+            _ => Err(self.error(format!("unknown directive `{}`", token))),
+        }
+    }
+
+    fn statement(&mut self, token: String) -> Result<(), LoadError> {
+        if token.starts_with(':') {
+            return self.directive(&token);
+        }
+
+        if let Some(x) = self.to_register(&token) {
+            return self.assign(x);
+        }
+
+        if self.macros.contains_key(&token) {
+            return self.expand(&token);
+        }
+
+        if let Some(n) = number(&token) {
+            return match u8::try_from(n).or_else(|_| i8::try_from(n).map(|n| n as u8)) {
+                Ok(byte) => self.emit(byte),
+                Err(_) => Err(self.error(format!("byte {} out of range", n))),
+            };
+        }
+
+        match token.as_str() {
+            "clear" => self.emit_op(0x00E0),
+            "return" | ";" => self.emit_op(0x00EE),
+            "hires" => self.emit_op(0x00FF),
+            "lores" => self.emit_op(0x00FE),
+            "exit" => self.emit_op(0x00FD),
+            "scroll-right" => self.emit_op(0x00FB),
+            "scroll-left" => self.emit_op(0x00FC),
+            "scroll-down" => {
+                let n = u16::from(self.nibble()?);
+
+                self.emit_op(0x00C0 | n)
+            }
+            "scroll-up" => {
+                let n = u16::from(self.nibble()?);
+
+                self.emit_op(0x00D0 | n)
+            }
+            "audio" => self.emit_op(0xF002),
+            "plane" => {
+                let n = u16::from(self.nibble()?);
+
+                self.emit_op(0xF001 | n << 8)
+            }
+            "bcd" => {
+                let x = u16::from(self.register()?);
+
+                self.emit_op(0xF033 | x << 8)
+            }
+            "save" => self.memory(0xF055, 0x5002),
+            "load" => self.memory(0xF065, 0x5003),
+            "saveflags" => {
+                let x = u16::from(self.register()?);
+
+                self.emit_op(0xF075 | x << 8)
+            }
+            "loadflags" => {
+                let x = u16::from(self.register()?);
+
+                self.emit_op(0xF085 | x << 8)
+            }
+            "sprite" => {
+                let x = u16::from(self.register()?);
+                let y = u16::from(self.register()?);
+                let n = u16::from(self.nibble()?);
+
+                self.emit_op(0xD000 | x << 8 | y << 4 | n)
+            }
+            "jump" => self.emit_nnn(0x1000),
+            "jump0" => self.emit_nnn(0xB000),
+            "native" => self.emit_nnn(0x0000),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+
+                let x = u16::from(self.register()?);
+                let low = match token.as_str() {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+
+                self.emit_op(0xF000 | x << 8 | low)
+            }
+            "i" => self.assign_i(),
+            "if" => {
+                let condition = self.condition()?;
+
+                match self.next()?.as_str() {
+                    // The skip jumps over the next statement
+                    "then" => self.emit_condition(&condition, false),
+                    "begin" => {
+                        self.emit_condition(&condition, true)?;
+
+                        let at = self.emit_placeholder()?;
+
+                        self.ifs.push(at);
+
+                        Ok(())
+                    }
+                    other => Err(self.error(format!("expected `then` or `begin`, found `{}`", other))),
+                }
+            }
+            "else" => {
+                let at = self.ifs.pop().ok_or_else(|| self.error("`else` without `if ... begin`"))?;
+                let end = self.emit_placeholder()?;
+
+                self.patch(at, Patch::Nnn, self.here);
+                self.ifs.push(end);
+
+                Ok(())
+            }
+            "end" => {
+                let at = self.ifs.pop().ok_or_else(|| self.error("`end` without `if ... begin`"))?;
+
+                self.patch(at, Patch::Nnn, self.here);
+
+                Ok(())
+            }
+            "loop" => {
+                self.loops.push((self.here, Vec::new()));
+
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+
+                if self.loops.is_empty() {
+                    return Err(self.error("`while` outside a loop"));
+                }
+
+                self.emit_condition(&condition, true)?;
+
+                let at = self.emit_placeholder()?;
+
+                if let Some((_, exits)) = self.loops.last_mut() {
+                    exits.push(at);
+                }
+
+                Ok(())
+            }
+            "again" => {
+                let (start, exits) = self.loops.pop().ok_or_else(|| self.error("`again` without `loop`"))?;
+
+                self.emit_op(0x1000 | start as u16)?;
+
+                for at in exits {
+                    self.patch(at, Patch::Nnn, self.here);
+                }
+
+                Ok(())
+            }
+            // Any other name calls the subroutine at that label
+            _ => {
+                self.tokens.push_front(Token { text: token, line: self.line });
+                self.emit_nnn(0x2000)
+            }
+        }
+    }
+
+    fn assemble(mut self) -> Result<Vec<u8>, LoadError> {
+        // Room for the jump to main
+        self.emit_op(0x1000)?;
+
+        while let Some(token) = self.tokens.pop_front() {
+            self.line = token.line;
+            self.statement(token.text)?;
+        }
+
+        if !self.ifs.is_empty() {
+            return Err(self.error("`if ... begin` without `end`"));
+        }
+
+        if !self.loops.is_empty() {
+            return Err(self.error("`loop` without `again`"));
+        }
+
+        let main = *self.labels.get("main").ok_or_else(|| self.error("no `main` label"))?;
+
+        self.patch(ORIGIN, Patch::Nnn, main);
+
+        for fixup in core::mem::take(&mut self.fixups) {
+            match self.labels.get(&fixup.label) {
+                Some(&addr) => self.patch(fixup.at, fixup.patch, addr),
+                None => {
+                    return Err(LoadError::OctoSource { line: fixup.line, reason: format!("undefined label `{}`", fixup.label) });
+                }
+            }
+        }
+
+        Ok(self.rom)
+    }
+}
+
+/// Assembles Octo source into a program loaded at 0x200
+pub fn assemble(source: &str) -> Result<Vec<u8>, LoadError> {
+    Assembler::new(source).assemble()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_blocks() {
+        let source = "
+            : main
+              v0 := 5
+              loop
+                v0 -= 1
+                if v0 == 2 then v1 := 7
+                while v0 != 0
+              again
+              i := sprite
+              sprite v0 v1 5
+              if v1 > 3 begin v2 := 1 else v2 := 2 end
+            : spin jump spin
+            : sprite 0xF0 0x90
+        ";
+
+        assert_eq!(assemble(source).unwrap(), [
+            0x12, 0x02, 0x60, 0x05, 0x70, 0xFF, 0x40, 0x02, 0x61, 0x07, 0x40, 0x00, 0x12, 0x10, 0x12, 0x04,
+            0xA2, 0x24, 0xD0, 0x15, 0x6F, 0x03, 0x8F, 0x15, 0x4F, 0x01, 0x12, 0x20, 0x62, 0x01, 0x12, 0x22,
+            0x62, 0x02, 0x12, 0x22, 0xF0, 0x90,
+        ]);
+    }
+
+    #[test]
+    fn macros_constants_and_unpack() {
+        let source = "
+            :const SPEED 3
+            :calc DOUBLE { SPEED * 2 + 1 }
+            :alias x v4
+            :macro add-to reg amount { reg += amount }
+            : main
+              add-to x DOUBLE
+              :unpack 0xA data
+              draw
+              i := long data
+            : draw ;
+            :org 0x300
+            : data :byte { 1 << 4 } :pointer draw
+        ";
+
+        assert_eq!(assemble(source).unwrap()[..0x10], [
+            0x12, 0x02, 0x74, 0x09, 0x60, 0xA3, 0x61, 0x00, 0x22, 0x0E, 0xF0, 0x00, 0x03, 0x00, 0x00, 0xEE,
+        ]);
+        assert_eq!(assemble(source).unwrap()[0x100..], [0x10, 0x02, 0x0E]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| match assemble(source) {
+            Err(LoadError::OctoSource { line, .. }) => line,
+            _ => panic!("`{}` assembled", source),
+        };
+
+        assert_eq!(error(": start clear"), 1);
+        assert_eq!(error(": main\n  jump nowhere"), 2);
+        assert_eq!(error(": main\n  if v0 == 1 begin\n  clear"), 3);
+        assert_eq!(error(": main\n  v0 := 256"), 2);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::loader::LoadError;
+
+use alloc::{vec, vec::Vec};
+
+/// Largest frame decoded, in pixels; cartridges are far smaller, and this bounds what a corrupt header can allocate
+const MAX_PIXELS: usize = 4096 * 4096;
+
+/// Decoded GIF image: palette indices of every frame, in file order and deinterlaced
+pub struct Gif {
+    pub frames: Vec<Vec<u8>>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        let b = *self.data.get(self.pos).ok_or(LoadError::Gif("unexpected end of file"))?;
+
+        self.pos += 1;
+
+        Ok(b)
+    }
+
+    fn word(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), LoadError> {
+        if self.pos + len > self.data.len() {
+            return Err(LoadError::Gif("unexpected end of file"));
+        }
+
+        self.pos += len;
+
+        Ok(())
+    }
+
+    /// Reads a chain of data sub-blocks
+    fn sub_blocks(&mut self) -> Result<Vec<u8>, LoadError> {
+        let mut data = Vec::new();
+
+        loop {
+            let len = self.byte()? as usize;
+
+            if len == 0 {
+                return Ok(data);
+            }
+
+            let start = self.pos;
+
+            self.skip(len)?;
+            data.extend_from_slice(&self.data[start..start + len]);
+        }
+    }
+}
+
+/// Decodes GIF LZW data into `len` palette indices
+fn lzw_decode(min_code_size: u8, data: &[u8], len: usize) -> Result<Vec<u8>, LoadError> {
+    const MAX_CODES: usize = 4096;
+
+    if !(2..=8).contains(&min_code_size) {
+        return Err(LoadError::Gif("invalid LZW code size"));
+    }
+
+    let clear = 1usize << min_code_size;
+    let end = clear + 1;
+
+    // Every code is a previous code plus one byte
+    let mut prefix = vec![0u16; MAX_CODES];
+    let mut suffix = vec![0u8; MAX_CODES];
+    let mut first = vec![0u8; MAX_CODES];
+    let mut lengths = vec![0u16; MAX_CODES];
+
+    for code in 0..clear {
+        (suffix[code], first[code], lengths[code]) = (code as u8, code as u8, 1);
+    }
+
+    let mut out = Vec::with_capacity(len);
+
+    let (mut next, mut width, mut prev): (usize, u32, Option<usize>) = (end + 1, min_code_size as u32 + 1, None);
+    let (mut acc, mut bits, mut pos): (u32, u32, usize) = (0, 0, 0);
+
+    while out.len() < len {
+        while bits < width {
+            let b = match data.get(pos) {
+                Some(b) => *b,
+                None => return Err(LoadError::Gif("truncated image data")),
+            };
+
+            acc |= (b as u32) << bits;
+            bits += 8;
+            pos += 1;
+        }
+
+        let code = (acc & ((1 << width) - 1)) as usize;
+
+        acc >>= width;
+        bits -= width;
+
+        if code == clear {
+            (next, width, prev) = (end + 1, min_code_size as u32 + 1, None);
+
+            continue;
+        }
+
+        if code == end {
+            break;
+        }
+
+        let head = match (prev, code) {
+            (_, code) if code < next && lengths[code] > 0 => first[code],
+            (Some(prev), code) if code == next => first[prev],
+            _ => return Err(LoadError::Gif("invalid LZW code")),
+        };
+
+        if let Some(prev) = prev {
+            if next < MAX_CODES {
+                prefix[next] = prev as u16;
+                suffix[next] = head;
+                first[next] = first[prev];
+                lengths[next] = lengths[prev] + 1;
+
+                next += 1;
+
+                if next == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+        }
+
+        // Emit the string for `code` by walking its prefix chain backwards
+        let start = out.len();
+        let mut walk = code;
+
+        out.resize(start + lengths[code] as usize, 0);
+
+        for i in (start..out.len()).rev() {
+            out[i] = suffix[walk];
+            walk = prefix[walk] as usize;
+        }
+
+        prev = Some(code);
+    }
+
+    out.resize(len, 0);
+
+    Ok(out)
+}
+
+/// Reorders rows of an interlaced image (passes of every 8th row from 0, 8th from 4, 4th from 2, 2nd from 1)
+fn deinterlace(pixels: Vec<u8>, width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0; pixels.len()];
+    let mut rows = pixels.chunks(width);
+
+    for (start, step) in [(0, 8), (4, 8), (2, 4), (1, 2)] {
+        for y in (start..height).step_by(step) {
+            if let Some(row) = rows.next() {
+                out[width * y..width * (y + 1)].copy_from_slice(row);
+            }
+        }
+    }
+
+    out
+}
+
+pub fn decode(data: &[u8]) -> Result<Gif, LoadError> {
+    let mut reader = Reader { data, pos: 6 };
+
+    // Logical screen size, background color index and pixel aspect ratio
+    reader.skip(4)?;
+    let packed = reader.byte()?;
+    reader.skip(2)?;
+
+    if packed & 0x80 != 0 {
+        reader.skip(3 << ((packed & 0x7) + 1))?;
+    }
+
+    let mut frames = Vec::new();
+
+    loop {
+        match reader.byte()? {
+            // Extension
+            0x21 => {
+                reader.byte()?;
+                reader.sub_blocks()?;
+            }
+            // Image descriptor
+            0x2C => {
+                reader.skip(4)?;
+
+                let (frame_width, frame_height) = (reader.word()? as usize, reader.word()? as usize);
+                let packed = reader.byte()?;
+
+                if frame_width == 0 || frame_height == 0 {
+                    return Err(LoadError::Gif("empty frame"));
+                }
+
+                if frame_width * frame_height > MAX_PIXELS {
+                    return Err(LoadError::Gif("frame too large"));
+                }
+
+                if packed & 0x80 != 0 {
+                    reader.skip(3 << ((packed & 0x7) + 1))?;
+                }
+
+                let min_code_size = reader.byte()?;
+                let lzw = reader.sub_blocks()?;
+                let pixels = lzw_decode(min_code_size, &lzw, frame_width * frame_height)?;
+
+                frames.push(if packed & 0x40 != 0 { deinterlace(pixels, frame_width, frame_height) } else { pixels });
+            }
+            // Trailer
+            0x3B => break,
+            _ => return Err(LoadError::Gif("unknown block")),
+        }
+    }
+
+    Ok(Gif { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::collections::BTreeMap;
+
+    /// GIF LZW encoder, growing the code width at the point the decoder does
+    fn lzw_encode(min_code_size: u8, pixels: &[u8]) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let mut width = u32::from(min_code_size) + 1;
+        let (mut out, mut acc, mut bits) = (Vec::new(), 0u32, 0);
+
+        let mut put = |code: u16, width: u32| {
+            acc |= u32::from(code) << bits;
+            bits += width;
+
+            while bits >= 8 {
+                out.push(acc as u8);
+                acc >>= 8;
+                bits -= 8;
+            }
+        };
+
+        let mut table = BTreeMap::new();
+        let mut next = clear + 2;
+        let mut emitted = 0;
+
+        put(clear, width);
+
+        let mut current = u16::from(pixels[0]);
+
+        for &pixel in &pixels[1..] {
+            if let Some(&code) = table.get(&(current, pixel)) {
+                current = code;
+
+                continue;
+            }
+
+            put(current, width);
+            emitted += 1;
+
+            if next == 4096 {
+                put(clear, width);
+                table.clear();
+                (next, width, emitted, current) = (clear + 2, u32::from(min_code_size) + 1, 0, u16::from(pixel));
+
+                continue;
+            }
+
+            // The decoder adds an entry for every code but the first, then widens once the next one needs it
+            if emitted > 1 && next == 1 << width && width < 12 {
+                width += 1;
+            }
+
+            table.insert((current, pixel), next);
+            next += 1;
+            current = u16::from(pixel);
+        }
+
+        put(current, width);
+        emitted += 1;
+
+        if emitted > 1 && next == 1 << width && width < 12 {
+            width += 1;
+        }
+
+        put(clear + 1, width);
+        put(0, 7);
+
+        out
+    }
+
+    /// Pseudo-random palette indices below 4
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8 & 0x3
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        // A run makes the encoder emit the code it is defining (the KwKwK case)
+        let run = [1; 5];
+
+        assert_eq!(lzw_decode(2, &lzw_encode(2, &run), run.len()).unwrap(), run);
+
+        // Long enough to fill the code table, which the encoder then clears
+        let pixels = noise(50_000);
+
+        assert_eq!(lzw_decode(2, &lzw_encode(2, &pixels), pixels.len()).unwrap(), pixels);
+    }
+
+    fn image(width: u16, height: u16, interlaced: bool, pixels: &[u8]) -> Vec<u8> {
+        let mut gif = b"GIF89a".to_vec();
+
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.extend_from_slice(&[0, 0, 0, 0x2C, 0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.extend_from_slice(&[if interlaced { 0x40 } else { 0 }, 2]);
+
+        for block in lzw_encode(2, pixels).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+
+        gif.extend_from_slice(&[0, 0x3B]);
+        gif
+    }
+
+    #[test]
+    fn interlaced() {
+        // Row y is filled with y % 4; an interlaced file stores rows 0, 8, 4, 2, 6, 1, 3, 5, 7, 9
+        let (width, height) = (3, 10);
+        let order = [0, 8, 4, 2, 6, 1, 3, 5, 7, 9];
+        let stored: Vec<u8> = order.iter().flat_map(|&y| vec![y as u8 % 4; width]).collect();
+        let expected: Vec<u8> = (0..height).flat_map(|y| vec![y as u8 % 4; width]).collect();
+
+        let frames = decode(&image(width as u16, height as u16, true, &stored)).unwrap().frames;
+
+        assert_eq!(frames, [expected]);
+    }
+
+    #[test]
+    fn frame_size() {
+        assert!(matches!(decode(&image(0, 4, true, &[0])), Err(LoadError::Gif("empty frame"))));
+        assert!(matches!(decode(&image(4, 0, false, &[0])), Err(LoadError::Gif("empty frame"))));
+        assert!(matches!(decode(&image(0xFFFF, 0xFFFF, false, &[0])), Err(LoadError::Gif("frame too large"))));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::loader::LoadError;
+
+use alloc::{string::ToString, vec::Vec};
+
+/// Decodes whitespace- or comma-separated hex bytes
+///
+/// Tokens may carry a `0x` or `$` prefix and hold several bytes (`00E0`). `#`, `;` and `//` start comments.
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, LoadError> {
+    let text = core::str::from_utf8(data).map_err(|_| LoadError::HexText { line: 1, token: "<binary>".to_string() })?;
+
+    let mut program = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = [line.find('#'), line.find(';'), line.find("//")]
+            .iter()
+            .flatten()
+            .min()
+            .map_or(line, |&end| &line[..end]);
+
+        for token in line.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()) {
+            let invalid = || LoadError::HexText { line: index + 1, token: token.to_string() };
+
+            let digits = token.strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .or_else(|| token.strip_prefix('$'))
+                .unwrap_or(token);
+
+            if digits.is_empty() || digits.len() % 2 != 0 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+
+            for i in (0..digits.len()).step_by(2) {
+                program.push(u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid())?);
+            }
+        }
+    }
+
+    if program.is_empty() {
+        return Err(LoadError::HexText { line: 1, token: "<empty>".to_string() });
+    }
+
+    Ok(program)
+}
@@ -0,0 +1,142 @@
+use crate::{Core, loader::{LoadError, Options}};
+
+use alloc::vec::Vec;
+
+/// Decodes Intel HEX records into a program image and the address it loads at
+///
+/// Addresses are absolute: the program starts at the lowest one, which becomes the load address (0x300 for CHIP-8X,
+/// 0x600 for ETI 660, ...). Files starting at 0 hold offsets into the program and keep the configured load address.
+/// Gaps are filled with zeroes.
+pub fn decode(data: &[u8]) -> Result<(Vec<u8>, Options), LoadError> {
+    let text = core::str::from_utf8(data).map_err(|_| LoadError::IntelHex { line: 1, reason: "not text" })?;
+
+    let mut base: u32 = 0;
+    let mut bytes: Vec<(u32, u8)> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |reason| LoadError::IntelHex { line: index + 1, reason };
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let digits = line.strip_prefix(':').ok_or_else(|| error("record does not start with `:`"))?;
+
+        if digits.len() % 2 != 0 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error("malformed hex digits"));
+        }
+
+        let record: Vec<u8> = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect();
+
+        if record.len() < 5 || record.len() != 5 + record[0] as usize {
+            return Err(error("record length mismatch"));
+        }
+
+        if record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(error("checksum mismatch"));
+        }
+
+        let addr = u16::from_be_bytes([record[1], record[2]]) as u32;
+        let payload = &record[4..record.len() - 1];
+
+        match record[3] {
+            // Data
+            0x00 => bytes.extend(payload.iter().enumerate().map(|(i, b)| (base + addr + i as u32, *b))),
+            // End of file
+            0x01 => break,
+            // Extended segment address
+            0x02 if payload.len() == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as u32) << 4,
+            // Extended linear address
+            0x04 if payload.len() == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as u32) << 16,
+            // Start segment/linear address, irrelevant to Chip-8
+            0x03 | 0x05 => {}
+            _ => return Err(error("unsupported record")),
+        }
+    }
+
+    let origin = bytes.iter().map(|(addr, _)| *addr).min().ok_or(LoadError::IntelHex { line: 1, reason: "no data records" })?;
+    let end = bytes.iter().map(|(addr, _)| *addr).max().unwrap_or(0) - origin + 1;
+
+    if origin > u16::MAX as u32 || end as usize > Core::MAX_ROM_SIZE {
+        return Err(LoadError::IntelHex { line: 1, reason: "program does not fit in memory" });
+    }
+
+    let mut program = alloc::vec![0; end as usize];
+
+    for (addr, b) in bytes {
+        program[(addr - origin) as usize] = b;
+    }
+
+    let options = Options { load_address: Some(origin as u16).filter(|&origin| origin != 0), ..Options::default() };
+
+    Ok((program, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::{format, string::String};
+
+    /// Formats a record with its checksum
+    fn record(addr: u16, kind: u8, payload: &[u8]) -> String {
+        let mut bytes = alloc::vec![payload.len() as u8];
+
+        bytes.extend_from_slice(&addr.to_be_bytes());
+        bytes.push(kind);
+        bytes.extend_from_slice(payload);
+        bytes.push(bytes.iter().fold(0u8, |sum, b| sum.wrapping_sub(*b)));
+
+        bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>()
+    }
+
+    fn reason(text: &str) -> Option<(usize, &'static str)> {
+        match decode(text.as_bytes()) {
+            Err(LoadError::IntelHex { line, reason }) => Some((line, reason)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn records() {
+        let text = format!(":{}\n:{}\n:{}\n", record(0x600, 0, &[0x00, 0xE0]), record(0x604, 0, &[0x16, 0x04]), record(0, 1, &[]));
+        let (program, options) = decode(text.as_bytes()).unwrap();
+
+        assert_eq!(program, [0x00, 0xE0, 0x00, 0x00, 0x16, 0x04]);
+        assert_eq!(options.load_address, Some(0x600));
+
+        // Offsets into the program
+        let (program, options) = decode(format!(":{}", record(0, 0, &[0x12, 0x00])).as_bytes()).unwrap();
+
+        assert_eq!(program, [0x12, 0x00]);
+        assert_eq!(options.load_address, None);
+
+        // Extended linear address past 64 KiB
+        let text = format!(":{}\n:{}", record(0, 4, &[0x00, 0x01]), record(0, 0, &[0x12, 0x00]));
+
+        assert_eq!(reason(&text), Some((1, "program does not fit in memory")));
+    }
+
+    #[test]
+    fn checksum() {
+        let mut line = record(0x200, 0, &[0x00, 0xE0]);
+
+        // Corrupt the last data digit
+        line.replace_range(11..12, "1");
+
+        assert_eq!(reason(&format!(":{}\n:{}", record(0x100, 0, &[1]), line)), Some((2, "checksum mismatch")));
+    }
+
+    #[test]
+    fn record_types() {
+        assert_eq!(reason(&format!(":{}", record(0, 6, &[]))), Some((1, "unsupported record")));
+        assert_eq!(reason(&format!(":{}", record(0, 4, &[0]))), Some((1, "unsupported record")));
+        assert_eq!(reason(&format!(":{}", record(0, 1, &[]))), Some((1, "no data records")));
+        assert_eq!(reason("0200000000E0"), Some((1, "record does not start with `:`")));
+        assert_eq!(reason(":0300000000E0"), Some((1, "record length mismatch")));
+    }
+}
@@ -0,0 +1,277 @@
+use alloc::{string::String, vec::Vec};
+
+/// Minimal JSON value, enough for Octo cartridge payloads
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+
+    /// Items are validated but not kept, no cartridge option uses them
+    Array,
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            // Octo stores some numeric options as strings
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Deepest nesting of arrays and objects accepted, which bounds the recursion of the parser
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+
+    /// Arrays and objects open around the current position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        if self.peek()? == b {
+            self.pos += 1;
+
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Option<Value> {
+        if self.text[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = core::str::from_utf8(self.text.get(self.pos..self.pos + 4)?).ok()?;
+
+        self.pos += 4;
+
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+
+        let mut s = String::new();
+
+        loop {
+            let start = self.pos;
+
+            // Copy unescaped runs in one go so that multi-byte UTF-8 sequences stay intact
+            while !matches!(self.text.get(self.pos)?, b'"' | b'\\') {
+                self.pos += 1;
+            }
+
+            s.push_str(core::str::from_utf8(&self.text[start..self.pos]).ok()?);
+
+            let b = self.text[self.pos];
+
+            self.pos += 1;
+
+            if b == b'"' {
+                return Some(s);
+            }
+
+            let escaped = *self.text.get(self.pos)?;
+
+            self.pos += 1;
+
+            match escaped {
+                b'n' => s.push('\n'),
+                b't' => s.push('\t'),
+                b'r' => s.push('\r'),
+                b'b' => s.push('\u{8}'),
+                b'f' => s.push('\u{c}'),
+                b'u' => {
+                    let mut code = self.hex4()?;
+
+                    // Surrogate pair
+                    if (0xD800..0xDC00).contains(&code) && self.text[self.pos..].starts_with(b"\\u") {
+                        self.pos += 2;
+
+                        let low = self.hex4()?.checked_sub(0xDC00).filter(|low| *low < 0x400)?;
+
+                        code = 0x10000 + ((code - 0xD800) << 10) + low;
+                    }
+
+                    s.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                }
+                other => s.push(other as char),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+
+        while self.text.get(self.pos).is_some_and(|b| matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+
+        core::str::from_utf8(&self.text[start..self.pos]).ok()?.parse().ok().map(Value::Number)
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+
+        self.depth += 1;
+
+        let value = self.item();
+
+        self.depth -= 1;
+
+        value
+    }
+
+    fn item(&mut self) -> Option<Value> {
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+
+                let mut members = Vec::new();
+
+                if self.peek()? == b'}' {
+                    self.pos += 1;
+
+                    return Some(Value::Object(members));
+                }
+
+                loop {
+                    let key = self.string()?;
+
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b'}' => {
+                            self.pos += 1;
+
+                            return Some(Value::Object(members));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+
+                if self.peek()? == b']' {
+                    self.pos += 1;
+
+                    return Some(Value::Array);
+                }
+
+                loop {
+                    self.value()?;
+
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b']' => {
+                            self.pos += 1;
+
+                            return Some(Value::Array);
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'"' => self.string().map(Value::String),
+            b't' => self.literal("true", Value::Bool(true)),
+            b'f' => self.literal("false", Value::Bool(false)),
+            b'n' => self.literal("null", Value::Null),
+            _ => self.number(),
+        }
+    }
+}
+
+pub fn parse(text: &[u8]) -> Option<Value> {
+    let mut parser = Parser { text, pos: 0, depth: 0 };
+    let value = parser.value()?;
+
+    match parser.peek() {
+        None => Some(value),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_and_surrogate_pairs() {
+        let root = parse(r#" { "program": "a\n\t\"\\\/\u00e9\ud83d\ude00 é", "n": -1.5e1 } "#.as_bytes()).unwrap();
+
+        assert_eq!(root.get("program").and_then(Value::as_str), Some("a\n\t\"\\/\u{e9}\u{1F600} \u{e9}"));
+        assert_eq!(root.get("n").and_then(Value::as_f64), Some(-15.0));
+
+        // A high surrogate followed by anything but a low one
+        assert!(parse(br#""\ud83d\u0041""#).is_none());
+        assert!(parse(br#""\u12""#).is_none());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth| {
+            let mut text = Vec::new();
+
+            text.resize(depth, b'[');
+            text.resize(2 * depth, b']');
+
+            parse(&text)
+        };
+
+        assert!(nested(MAX_DEPTH).is_some());
+        assert!(nested(MAX_DEPTH + 1).is_none());
+        assert!(nested(100_000).is_none());
+    }
+}
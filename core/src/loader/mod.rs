@@ -0,0 +1,155 @@
+use crate::{config::{Config, RplFlags, Variant}, font::Font};
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+mod assembler;
+mod gif;
+mod hextext;
+mod ihex;
+mod json;
+mod octo;
+
+/// ROM container formats understood by the loader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Raw program bytes
+    Binary,
+
+    /// Octo cartridge: a GIF image carrying the program and its options
+    OctoCartridge,
+
+    /// Intel HEX records
+    IntelHex,
+
+    /// Whitespace-separated hex bytes
+    HexText,
+}
+
+/// Settings embedded in a ROM container; `None` leaves the configuration untouched
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub variant: Option<Variant>,
+    pub steps: Option<usize>,
+    pub unlit_color: Option<u32>,
+    pub lit_color: Option<u32>,
+    pub shift_quirk: Option<bool>,
+    pub load_store_quirk: Option<bool>,
+    pub jump_quirk: Option<bool>,
+    pub logic_quirk: Option<bool>,
+    pub clip_quirk: Option<bool>,
+    pub vblank_quirk: Option<bool>,
+    pub vf_order_quirk: Option<bool>,
+    pub load_address: Option<u16>,
+
+    /// Defaults to the load address when only that is given
+    pub entry_point: Option<u16>,
+
+    pub vip_memory_map: Option<bool>,
+    pub font: Option<Font>,
+    pub font_address: Option<u16>,
+    pub rpl_flags: Option<RplFlags>,
+}
+
+impl Options {
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Copy>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        // First, since it moves the load address and entry point
+        if let Some(variant) = self.variant {
+            config.set_variant(variant);
+        }
+
+        set(&mut config.steps, self.steps);
+        set(&mut config.palette[0], self.unlit_color);
+        set(&mut config.palette[1], self.lit_color);
+        set(&mut config.quirks.shift, self.shift_quirk);
+        set(&mut config.quirks.load_store, self.load_store_quirk);
+        set(&mut config.quirks.jump, self.jump_quirk);
+        set(&mut config.quirks.logic, self.logic_quirk);
+        set(&mut config.quirks.clip, self.clip_quirk);
+        set(&mut config.quirks.vblank, self.vblank_quirk);
+        set(&mut config.quirks.vf_order, self.vf_order_quirk);
+        set(&mut config.load_address, self.load_address);
+        set(&mut config.entry_point, self.entry_point.or(self.load_address));
+        set(&mut config.vip_memory_map, self.vip_memory_map);
+        set(&mut config.font, self.font);
+        set(&mut config.font_address, self.font_address);
+        set(&mut config.rpl_flags, self.rpl_flags);
+    }
+}
+
+/// Decoded ROM
+pub struct Rom {
+    pub format: Format,
+    pub program: Vec<u8>,
+    pub options: Options,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Gif(&'static str),
+    Cartridge(&'static str),
+
+    /// Cartridge source that does not assemble
+    OctoSource { line: usize, reason: String },
+
+    IntelHex { line: usize, reason: &'static str },
+    HexText { line: usize, token: String },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Gif(reason) => write!(f, "invalid GIF: {}", reason),
+            LoadError::Cartridge(reason) => write!(f, "invalid Octo cartridge: {}", reason),
+            LoadError::OctoSource { line, reason } => write!(f, "Octo cartridge source line {}: {}", line, reason),
+            LoadError::IntelHex { line, reason } => write!(f, "invalid Intel HEX on line {}: {}", line, reason),
+            LoadError::HexText { line, token } => write!(f, "invalid hex byte `{}` on line {}", token, line),
+        }
+    }
+}
+
+/// Returns true if every byte is printable ASCII or whitespace
+fn is_text(data: &[u8]) -> bool {
+    data.iter().all(|&b| (0x20..0x7F).contains(&b) || matches!(b, b'\t' | b'\n' | b'\r'))
+}
+
+/// Detects the container format from the content
+pub fn detect(data: &[u8]) -> Format {
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Format::OctoCartridge;
+    }
+
+    if !data.is_empty() && is_text(data) {
+        let first = data.iter().find(|b| !b.is_ascii_whitespace());
+
+        if first == Some(&b':') {
+            return Format::IntelHex;
+        }
+
+        if hextext::decode(data).is_ok() {
+            return Format::HexText;
+        }
+    }
+
+    Format::Binary
+}
+
+/// Detects the container format and decodes the program
+pub fn load(data: &[u8]) -> Result<Rom, LoadError> {
+    let format = detect(data);
+
+    let (program, options) = match format {
+        Format::Binary => (data.to_vec(), Options::default()),
+        Format::OctoCartridge => octo::decode(data)?,
+        Format::IntelHex => ihex::decode(data)?,
+        Format::HexText => (hextext::decode(data)?, Options::default()),
+    };
+
+    Ok(Rom { format, program, options })
+}
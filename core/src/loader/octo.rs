@@ -0,0 +1,73 @@
+use crate::loader::{LoadError, Options, assembler, gif, json::{self, Value}};
+
+use alloc::vec::Vec;
+
+/// Extracts the payload hidden in the pixels of a cartridge
+///
+/// Each payload byte is spread over 4 consecutive pixels of the frames, two bits per pixel (most significant first)
+/// in the low bits of the palette index. The payload starts with its length as a 32-bit big-endian integer.
+fn payload(image: &gif::Gif) -> Result<Vec<u8>, LoadError> {
+    let bytes: Vec<u8> = image.frames
+        .iter()
+        .flat_map(|frame| frame.chunks_exact(4))
+        .map(|pixels| pixels.iter().fold(0, |byte, pixel| (byte << 2) | (pixel & 0x3)))
+        .collect();
+
+    if bytes.len() < 4 {
+        return Err(LoadError::Cartridge("image too small"));
+    }
+
+    let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+
+    bytes.get(4..4 + len).map(<[u8]>::to_vec).ok_or(LoadError::Cartridge("payload length exceeds image"))
+}
+
+/// Parses an Octo `#RRGGBB` color
+fn color(value: &Value) -> Option<u32> {
+    let hex = value.as_str()?.strip_prefix('#')?;
+
+    match hex.len() {
+        6 => u32::from_str_radix(hex, 16).ok(),
+        // #RGB shorthand
+        3 => u32::from_str_radix(hex, 16).ok().map(|rgb| {
+            let (r, g, b) = ((rgb >> 8) & 0xF, (rgb >> 4) & 0xF, rgb & 0xF);
+
+            (r * 0x11) << 16 | (g * 0x11) << 8 | (b * 0x11)
+        }),
+        _ => None,
+    }
+}
+
+/// Maps Octo's option names onto the machine configuration
+fn options(value: &Value) -> Options {
+    let flag = |key| value.get(key).and_then(Value::as_bool);
+
+    Options {
+        steps: value.get("tickrate").and_then(Value::as_f64).filter(|n| *n >= 1.0).map(|n| n as usize),
+        unlit_color: value.get("backgroundColor").and_then(color),
+        lit_color: value.get("fillColor").and_then(color),
+        shift_quirk: flag("shiftQuirks"),
+        load_store_quirk: flag("loadStoreQuirks"),
+        jump_quirk: flag("jumpQuirks"),
+        logic_quirk: flag("logicQuirks"),
+        clip_quirk: flag("clipQuirks"),
+        vblank_quirk: flag("vBlankQuirks"),
+        vf_order_quirk: flag("vfOrderQuirks"),
+        font: value.get("fontStyle").and_then(Value::as_str).and_then(|style| style.parse().ok()),
+        ..Options::default()
+    }
+}
+
+/// Decodes an Octo cartridge GIF into its program and options
+pub fn decode(data: &[u8]) -> Result<(Vec<u8>, Options), LoadError> {
+    let image = gif::decode(data)?;
+    let payload = payload(&image)?;
+
+    let root = json::parse(&payload).ok_or(LoadError::Cartridge("payload is not JSON"))?;
+    let source = root.get("program").and_then(Value::as_str).ok_or(LoadError::Cartridge("no program in payload"))?;
+
+    // Older cartridges keep the options next to the program instead of in their own object
+    let options = options(root.get("options").unwrap_or(&root));
+
+    Ok((assembler::assemble(source)?, options))
+}
@@ -0,0 +1,301 @@
+use crate::{
+    bus::memory::Memory,
+    cdp1802::{Cdp1802, System},
+    display::Display,
+    keypad::Keypad,
+};
+
+use core::{convert::TryFrom, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VipError {
+    /// The monitor ROM image is not 512 bytes
+    MonitorSize(usize),
+
+    /// The interpreter and program overlap or do not fit in RAM
+    ProgramTooLarge { len: usize, max: usize },
+}
+
+impl fmt::Display for VipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VipError::MonitorSize(len) => write!(f, "monitor ROM is {} bytes, expected {}", len, Vip::MONITOR_SIZE),
+            VipError::ProgramTooLarge { len, max } => write!(f, "{} bytes do not fit in {} bytes", len, max),
+        }
+    }
+}
+
+/// Everything on the VIP board besides the CPU: RAM, monitor ROM, CDP1861 state, keypad latch and tone
+struct Board {
+    ram: Memory,
+    monitor: [u8; Vip::MONITOR_SIZE],
+
+    /// Set on reset, maps the monitor over all of memory until the first access with A15 high
+    monitor_low: bool,
+
+    keypad: Keypad,
+
+    /// Key selected by OUT 2, read back on EF3
+    key_latch: u8,
+
+    /// CDP1861 on (INP 1) or off (OUT 1)
+    display_on: bool,
+
+    /// CDP1861 display status on EF1
+    ef1: bool,
+
+    /// Q drives the tone generator
+    q: bool,
+
+    /// 64x128 raster filled by DMA
+    raster: Display,
+}
+
+impl System for Board {
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr & 0x8000 != 0 {
+            self.monitor_low = false;
+        }
+
+        if addr & 0x8000 != 0 || self.monitor_low {
+            self.monitor[addr as usize % Vip::MONITOR_SIZE]
+        } else {
+            self.ram[addr as usize & (self.ram.len() - 1)]
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if addr & 0x8000 != 0 {
+            self.monitor_low = false;
+
+            return;
+        }
+
+        let len = self.ram.len();
+
+        self.ram[addr as usize & (len - 1)] = data;
+    }
+
+    fn input(&mut self, port: u8) -> u8 {
+        if port == 1 {
+            self.display_on = true;
+        }
+
+        0
+    }
+
+    fn output(&mut self, port: u8, data: u8) {
+        match port {
+            1 => self.display_on = false,
+            2 => self.key_latch = data & 0xF,
+            _ => {}
+        }
+    }
+
+    fn flag(&mut self, flag: u8) -> bool {
+        match flag {
+            1 => self.ef1,
+            3 => self.keypad.is_key_pressed(self.key_latch as usize),
+            _ => false,
+        }
+    }
+
+    fn set_q(&mut self, q: bool) {
+        self.q = q;
+    }
+}
+
+/// Low-level RCA COSMAC VIP: a CDP1802 running the monitor ROM and the original Chip-8 interpreter from RAM
+///
+/// Timing follows the CDP1861 in NTSC mode: 262 lines of 14 machine cycles per 60 Hz frame. During the 128 display
+/// lines the 1861 takes 8 of those cycles for DMA, and it interrupts the CPU 29 cycles before the first of them so
+/// that the interpreter's interrupt routine can set up R0.
+pub struct Vip {
+    cpu: Cdp1802,
+    board: Board,
+
+    /// Machine cycles run in the current frame
+    cycle: u32,
+}
+
+impl Vip {
+    pub const MONITOR_SIZE: usize = 0x200;
+    pub const RAM_SIZE: usize = 0x1000;
+
+    /// Where the interpreter expects Chip-8 programs
+    pub const PROGRAM_START: usize = 0x200;
+
+    /// Raster geometry; the interpreter repeats each Chip-8 row on 4 lines
+    pub const RASTER_WIDTH: usize = 64;
+    pub const RASTER_HEIGHT: usize = 128;
+
+    const LINES: u32 = 262;
+    const CYCLES_PER_LINE: u32 = 14;
+    const FRAME_CYCLES: u32 = Self::LINES * Self::CYCLES_PER_LINE;
+
+    const FIRST_DISPLAY_LINE: u32 = 80;
+    const DMA_CYCLES: u32 = 8;
+
+    /// The interrupt acknowledge cycle and the 29 cycles of the interrupt routine before the first DMA
+    const INTERRUPT_CYCLE: u32 = Self::FIRST_DISPLAY_LINE * Self::CYCLES_PER_LINE - 30;
+
+    /// EF1 is asserted for 4 lines before the display starts and 4 lines before it ends
+    const EF1_LINES: [u32; 2] = [Self::FIRST_DISPLAY_LINE - 4, Self::FIRST_DISPLAY_LINE + Self::RASTER_HEIGHT as u32 - 4];
+
+    /// Creates a VIP with the interpreter at 0x000 and the program at 0x200, reset into the monitor
+    ///
+    /// The monitor hands over to the interpreter at 0x000 unless key C is held, as on the real machine.
+    pub fn new(monitor: &[u8], interpreter: &[u8], program: &[u8]) -> Result<Self, VipError> {
+        let monitor = <[u8; Self::MONITOR_SIZE]>::try_from(monitor).map_err(|_| VipError::MonitorSize(monitor.len()))?;
+
+        if interpreter.len() > Self::PROGRAM_START {
+            return Err(VipError::ProgramTooLarge { len: interpreter.len(), max: Self::PROGRAM_START });
+        }
+
+        if program.len() > Self::RAM_SIZE - Self::PROGRAM_START {
+            return Err(VipError::ProgramTooLarge { len: program.len(), max: Self::RAM_SIZE - Self::PROGRAM_START });
+        }
+
+        let mut ram = Memory::new(Self::RAM_SIZE);
+
+        ram[0..interpreter.len()].copy_from_slice(interpreter);
+        ram[Self::PROGRAM_START..Self::PROGRAM_START + program.len()].copy_from_slice(program);
+
+        let board = Board {
+            ram,
+            monitor,
+            monitor_low: true,
+            keypad: Keypad::default(),
+            key_latch: 0,
+            display_on: false,
+            ef1: false,
+            q: false,
+            raster: Display::new(Self::RASTER_WIDTH, Self::RASTER_HEIGHT),
+        };
+
+        Ok(Self { cpu: Cdp1802::default(), board, cycle: 0 })
+    }
+
+    /// Runs instructions until `cycle` is reached (the last one may overrun it)
+    fn run_until(&mut self, cycle: u32) {
+        while self.cycle < cycle {
+            self.cycle += self.cpu.step(&mut self.board);
+        }
+    }
+
+    /// Transfers one line of 8 bytes from R0 into the raster
+    fn dma(&mut self, row: usize) {
+        for byte in 0..Self::RASTER_WIDTH / 8 {
+            let data = self.cpu.dma_out(&mut self.board);
+
+            for bit in 0..8 {
+                let lit = data & (0x80 >> bit) != 0;
+
+                self.board.raster[Self::RASTER_WIDTH * row + 8 * byte + bit] = Display::COLOR_WHITE * lit as u32;
+            }
+        }
+
+        self.cycle += Self::DMA_CYCLES;
+    }
+
+    /// Emulates one 60 Hz frame
+    pub fn frame(&mut self) {
+        self.board.raster.as_mut_slice().fill(0);
+
+        for line in 0..Self::LINES {
+            let start = line * Self::CYCLES_PER_LINE;
+
+            self.run_until(start);
+
+            self.board.ef1 = self.board.display_on && Self::EF1_LINES.iter().any(|&first| (first..first + 4).contains(&line));
+
+            let row = line.wrapping_sub(Self::FIRST_DISPLAY_LINE) as usize;
+
+            if self.board.display_on && row < Self::RASTER_HEIGHT {
+                self.dma(row);
+            }
+
+            if (start..start + Self::CYCLES_PER_LINE).contains(&Self::INTERRUPT_CYCLE) {
+                self.run_until(Self::INTERRUPT_CYCLE);
+
+                if self.board.display_on && self.cpu.interrupt() {
+                    self.cycle += 1;
+                }
+            }
+        }
+
+        self.run_until(Self::FRAME_CYCLES);
+        self.cycle -= Self::FRAME_CYCLES;
+    }
+
+    /// Returns the 64x128 raster of the last frame (blank while the CDP1861 is off)
+    pub fn raster(&self) -> &Display {
+        &self.board.raster
+    }
+
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.board.keypad
+    }
+
+    pub fn ram(&self) -> &Memory {
+        &self.board.ram
+    }
+
+    pub fn cpu(&self) -> &Cdp1802 {
+        &self.cpu
+    }
+
+    /// The tone generator sounds while Q is set
+    pub fn is_sound_active(&self) -> bool {
+        self.board.q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader;
+
+    use alloc::vec::Vec;
+
+    fn image(listing: &[u8]) -> Vec<u8> {
+        loader::load(listing).unwrap().program
+    }
+
+    fn stub_vip(program: &[u8]) -> Vip {
+        let monitor = image(include_bytes!("../../../conformance/vip/monitor.hex"));
+        let interpreter = image(include_bytes!("../../../conformance/vip/interpreter.hex"));
+
+        Vip::new(&monitor, &interpreter, program).unwrap()
+    }
+
+    /// Reads the 8 raster pixels of byte `column` on `line` back into a byte
+    fn raster_byte(vip: &Vip, line: usize, column: usize) -> u8 {
+        (0..8).fold(0, |byte, bit| byte << 1 | (vip.raster()[Vip::RASTER_WIDTH * line + 8 * column + bit] != 0) as u8)
+    }
+
+    #[test]
+    fn stub_interpreter() {
+        // I = sprite, V0 = 8, V1 = 31, draw 2 rows (the second is clipped), loop
+        let mut vip = stub_vip(&[0xA2, 0x0A, 0x60, 0x08, 0x61, 0x1F, 0xD0, 0x12, 0x12, 0x08, 0xA5, 0xFF]);
+
+        vip.frame();
+
+        // Every row is shown on 4 lines, including the last one
+        for line in 0..Vip::RASTER_HEIGHT {
+            assert_eq!(raster_byte(&vip, line, 1), if line >= 124 { 0xA5 } else { 0 }, "line {}", line);
+        }
+
+        assert_eq!(vip.ram()[0xEFF], 0);
+
+        // Held at reset, key C keeps the monitor from starting the interpreter, so the display stays off
+        let mut vip = stub_vip(&[0x12, 0x00]);
+
+        vip.keypad_mut().update_state([Keypad::grid_index(0xC)]);
+        vip.frame();
+
+        assert!(vip.cpu().idle);
+        assert!(vip.raster().as_slice().iter().all(|&pixel| pixel == 0));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::capture::{wav::WavWriter, y4m::Y4mWriter};
+
+use gif::{Encoder, Frame, Repeat};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+mod wav;
+mod y4m;
+
+/// Returns a fresh timestamped capture path in the current directory, e.g. `myuchip-1718000000123.png`
+pub fn capture_path(extension: &str) -> PathBuf {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis());
+
+    PathBuf::from(format!("myuchip-{}.{}", millis, extension))
+}
+
+/// Nearest-neighbour upscale of a 0RGB buffer to `scale` times its size
+fn upscale(buffer: &[u32], width: usize, height: usize, scale: usize) -> Vec<u32> {
+    let mut scaled = Vec::with_capacity(buffer.len() * scale * scale);
+
+    for y in 0..height * scale {
+        let row = &buffer[width * (y / scale)..width * (y / scale + 1)];
+
+        for x in 0..width * scale {
+            scaled.push(row[x / scale]);
+        }
+    }
+
+    scaled
+}
+
+fn rgb(pixel: u32) -> [u8; 3] {
+    let [_, r, g, b] = pixel.to_be_bytes();
+
+    [r, g, b]
+}
+
+/// Saves a 0RGB buffer as a PNG, scaled by `scale` without filtering so pixels stay sharp
+pub fn screenshot(path: &Path, buffer: &[u32], width: usize, height: usize, scale: usize) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), (width * scale) as u32, (height * scale) as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = upscale(buffer, width, height, scale).into_iter().flat_map(rgb).collect();
+
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(|e| e.to_string())
+}
+
+/// Records emulated frames into an animated GIF
+///
+/// GIF delays are in 1/100 s, so every frame is kept and the delays cycle through 2, 2 and 1 centiseconds: 5 cs per 3
+/// frames, exactly 60 Hz on average. Identical consecutive frames are merged into one longer frame.
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    width: usize,
+    height: usize,
+    scale: usize,
+
+    /// Frame waiting for its duration to be known, and the number of 60 Hz frames it has been shown for
+    pending: Option<(Vec<u32>, u64)>,
+
+    /// 60 Hz frames written before the pending one
+    written: u64,
+}
+
+impl GifRecorder {
+    /// Time at which 60 Hz frame `frame` starts, in centiseconds, rounded up
+    fn centiseconds(frame: u64) -> u64 {
+        (100 * frame).div_ceil(60)
+    }
+
+    pub fn new(path: &Path, width: usize, height: usize, scale: usize) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+
+        let mut encoder = Encoder::new(BufWriter::new(file), (width * scale) as u16, (height * scale) as u16, &[])
+            .map_err(|e| e.to_string())?;
+
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+
+        Ok(Self { encoder, width, height, scale, pending: None, written: 0 })
+    }
+
+    /// Adds one emulated frame
+    pub fn push(&mut self, buffer: &[u32]) -> Result<(), String> {
+        match &mut self.pending {
+            Some((pending, frames)) if pending.as_slice() == buffer => {
+                *frames += 1;
+
+                Ok(())
+            }
+            _ => {
+                let result = self.flush();
+
+                self.pending = Some((buffer.to_vec(), 1));
+
+                result
+            }
+        }
+    }
+
+    /// Writes the pending frame
+    fn flush(&mut self) -> Result<(), String> {
+        let (buffer, frames) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+
+        let delay = Self::centiseconds(self.written + frames) - Self::centiseconds(self.written);
+
+        self.written += frames;
+
+        // Build a local palette from the colors in use (Chip-8 frames only ever have a handful)
+        let mut palette: HashMap<u32, u8> = HashMap::new();
+        let mut colors = Vec::new();
+
+        for &pixel in buffer.iter() {
+            if !palette.contains_key(&pixel) {
+                if palette.len() == 256 {
+                    return Err("Too many colors for a GIF frame".into());
+                }
+
+                palette.insert(pixel, palette.len() as u8);
+                colors.extend_from_slice(&rgb(pixel));
+            }
+        }
+
+        let indices: Vec<u8> = upscale(&buffer, self.width, self.height, self.scale).iter().map(|pixel| palette[pixel]).collect();
+
+        let mut frame = Frame::from_palette_pixels((self.width * self.scale) as u16, (self.height * self.scale) as u16, indices, colors, None);
+        frame.delay = delay.min(u16::MAX as u64) as u16;
+
+        self.encoder.write_frame(&frame).map_err(|e| e.to_string())
+    }
+
+    /// Writes the last frame and closes the file
+    pub fn finish(mut self) -> Result<(), String> {
+        self.flush()
+    }
+}
+
+/// Square wave generator driven by the sound timer, continuous in phase across frames
+struct Beeper {
+    /// Samples generated so far
+    position: u64,
+}
+
+impl Beeper {
+    const TONE: u64 = 440;
+    const AMPLITUDE: i16 = i16::MAX / 4;
+
+    /// Generates the next `samples.len()` samples at `sample_rate`
+    fn fill(&mut self, samples: &mut [i16], sample_rate: u64, active: bool) {
+        for sample in samples.iter_mut() {
+            let half_period = self.position * 2 * Self::TONE / sample_rate;
+
+            *sample = match (active, half_period.is_multiple_of(2)) {
+                (false, _) => 0,
+                (true, true) => Self::AMPLITUDE,
+                (true, false) => -Self::AMPLITUDE,
+            };
+
+            self.position += 1;
+        }
+    }
+}
+
+/// Lossless capture of every emulated frame to `<stem>.y4m` and of the sound to a sample-accurate `<stem>.wav`
+pub struct AvRecorder {
+    video: Y4mWriter,
+    audio: WavWriter,
+    beeper: Beeper,
+}
+
+impl AvRecorder {
+    const FPS: u32 = 60;
+    const SAMPLE_RATE: u32 = 44100;
+
+    /// 44100 / 60 divides evenly, so audio and video never drift apart
+    const SAMPLES_PER_FRAME: usize = (Self::SAMPLE_RATE / Self::FPS) as usize;
+
+    pub fn new(stem: &Path, width: usize, height: usize, scale: usize) -> Result<Self, String> {
+        Ok(Self {
+            video: Y4mWriter::new(&stem.with_extension("y4m"), width, height, scale, Self::FPS)?,
+            audio: WavWriter::new(&stem.with_extension("wav"), Self::SAMPLE_RATE)?,
+            beeper: Beeper { position: 0 },
+        })
+    }
+
+    /// Adds one emulated frame along with whether the beeper was on during it
+    ///
+    /// `mix` adds any other sound of the frame to the beeper samples, given the sample rate.
+    pub fn push<F: FnOnce(&mut [i16], u32)>(&mut self, buffer: &[u32], sound_active: bool, mix: F) -> Result<(), String> {
+        let mut samples = [0i16; Self::SAMPLES_PER_FRAME];
+
+        self.beeper.fill(&mut samples, Self::SAMPLE_RATE as u64, sound_active);
+        mix(&mut samples, Self::SAMPLE_RATE);
+
+        self.video.push(buffer)?;
+        self.audio.push(&samples)
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        self.video.finish()?;
+        self.audio.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_timing() {
+        let path = std::env::temp_dir().join(format!("myuchip-gif-timing-{}.gif", std::process::id()));
+        let mut recorder = GifRecorder::new(&path, 2, 1, 1).unwrap();
+
+        // 6 distinct frames, then 6 identical ones
+        for frame in 0..6 {
+            recorder.push(&[frame, 0]).unwrap();
+        }
+
+        for _ in 0..6 {
+            recorder.push(&[0xFF, 0]).unwrap();
+        }
+
+        recorder.finish().unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        let mut delays = Vec::new();
+
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(delays, [2, 2, 1, 2, 2, 1, 10]);
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// 16-bit mono PCM WAV writer; the header sizes are patched in on `finish`
+pub struct WavWriter {
+    out: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavWriter {
+    const HEADER_SIZE: u32 = 44;
+
+    pub fn new(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+
+        let mut header = Vec::with_capacity(Self::HEADER_SIZE as usize);
+
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        header.extend_from_slice(&1u16.to_le_bytes()); // Mono
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // Byte rate
+        header.extend_from_slice(&2u16.to_le_bytes()); // Block align
+        header.extend_from_slice(&16u16.to_le_bytes()); // Bits per sample
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+
+        out.write_all(&header).map_err(|e| e.to_string())?;
+
+        Ok(Self { out, samples: 0 })
+    }
+
+    pub fn push(&mut self, samples: &[i16]) -> Result<(), String> {
+        for sample in samples {
+            self.out.write_all(&sample.to_le_bytes()).map_err(|e| e.to_string())?;
+        }
+
+        self.samples += samples.len() as u32;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        let data_size = self.samples * 2;
+
+        let mut patch = |offset: u64, value: u32| -> std::io::Result<()> {
+            self.out.seek(SeekFrom::Start(offset))?;
+            self.out.write_all(&value.to_le_bytes())
+        };
+
+        patch(4, Self::HEADER_SIZE - 8 + data_size)
+            .and_then(|_| patch(40, data_size))
+            .and_then(|_| self.out.flush())
+            .map_err(|e| e.to_string())
+    }
+}
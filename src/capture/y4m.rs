@@ -0,0 +1,60 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Uncompressed YUV4MPEG2 (Y4M) video writer, 4:4:4 so that no chroma is lost on pixel edges
+pub struct Y4mWriter {
+    out: BufWriter<File>,
+    width: usize,
+    height: usize,
+    scale: usize,
+}
+
+impl Y4mWriter {
+    pub fn new(path: &Path, width: usize, height: usize, scale: usize, fps: u32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+
+        writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", width * scale, height * scale, fps).map_err(|e| e.to_string())?;
+
+        Ok(Self { out, width, height, scale })
+    }
+
+    /// BT.601 limited-range conversion of a 0RGB pixel
+    fn yuv(pixel: u32) -> [u8; 3] {
+        let [_, r, g, b] = pixel.to_be_bytes();
+        let (r, g, b) = (r as i32, g as i32, b as i32);
+
+        let y = 16 + ((66 * r + 129 * g + 25 * b + 128) >> 8);
+        let u = 128 + ((-38 * r - 74 * g + 112 * b + 128) >> 8);
+        let v = 128 + ((112 * r - 94 * g - 18 * b + 128) >> 8);
+
+        [y as u8, u as u8, v as u8]
+    }
+
+    /// Writes one frame from a 0RGB buffer
+    pub fn push(&mut self, buffer: &[u32]) -> Result<(), String> {
+        let (width, height) = (self.width * self.scale, self.height * self.scale);
+        let mut planes = vec![0u8; width * height * 3];
+
+        for y in 0..height {
+            for x in 0..width {
+                let yuv = Self::yuv(buffer[self.width * (y / self.scale) + x / self.scale]);
+
+                for (plane, value) in yuv.iter().enumerate() {
+                    planes[width * height * plane + width * y + x] = *value;
+                }
+            }
+        }
+
+        self.out.write_all(b"FRAME\n")
+            .and_then(|_| self.out.write_all(&planes))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
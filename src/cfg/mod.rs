@@ -0,0 +1,129 @@
+use myuchip_core::{config::{Config, Variant}, disasm, flow::{self, ControlFlowGraph, Exit}, loader};
+
+use std::{collections::{BTreeMap, BTreeSet}, fmt::Write as _, path::Path};
+
+fn node(addr: u16) -> String {
+    format!("b{:03X}", addr)
+}
+
+/// Renders the graph as Graphviz DOT, one cluster per subroutine
+///
+/// Blocks reached from several subroutines are drawn in the one with the lowest entry address. Computed jumps end in
+/// a red `?` node and blocks with self-modifying stores are outlined in red.
+fn dot(graph: &ControlFlowGraph, program: &[u8], config: &Config) -> String {
+    let opcode = |addr: u16| {
+        let offset = (addr - config.load_address) as usize;
+
+        u16::from_be_bytes([program[offset], program[offset + 1]])
+    };
+
+    let mut owners: BTreeMap<u16, u16> = BTreeMap::new();
+
+    for (&routine, blocks) in graph.subroutines.iter() {
+        for &block in blocks {
+            owners.entry(block).or_insert(routine);
+        }
+    }
+
+    let mut out = String::new();
+
+    writeln!(out, "digraph cfg {{").unwrap();
+    writeln!(out, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    for &routine in graph.subroutines.keys() {
+        let label = if routine == graph.entry { format!("main ({:03X})", routine) } else { format!("sub_{:03X}", routine) };
+
+        writeln!(out, "\n  subgraph cluster_{:03X} {{", routine).unwrap();
+        writeln!(out, "    label=\"{}\";", label).unwrap();
+
+        for block in graph.blocks.values().filter(|block| owners.get(&block.start) == Some(&routine)) {
+            let mut text = String::new();
+
+            for &addr in block.instructions.iter() {
+                write!(text, "{:03X}: {}\\l", addr, disasm::disassemble(opcode(addr), config.variant)).unwrap();
+            }
+
+            let modifies_code = graph.code_writes.iter().any(|write| block.instructions.contains(&write.at));
+            let color = if modifies_code { ", color=red" } else { "" };
+
+            writeln!(out, "    {} [label=\"{}\"{}];", node(block.start), text, color).unwrap();
+        }
+
+        writeln!(out, "  }}").unwrap();
+    }
+
+    writeln!(out).unwrap();
+
+    for block in graph.blocks.values() {
+        let from = node(block.start);
+        let last = block.instructions.last().copied().unwrap_or(block.start);
+
+        for exit in block.exits.iter() {
+            match *exit {
+                Exit::Fallthrough(target) => writeln!(out, "  {} -> {};", from, node(target)),
+                Exit::Jump(target) => writeln!(out, "  {} -> {} [label=\"jump\"];", from, node(target)),
+                Exit::Call(target) => writeln!(out, "  {} -> {} [label=\"call\", style=dashed];", from, node(target)),
+                Exit::Skip(target) => writeln!(out, "  {} -> {} [label=\"skip\"];", from, node(target)),
+                Exit::Computed => {
+                    writeln!(out, "  computed_{:03X} [label=\"?\", shape=diamond, color=red];", last).unwrap();
+                    writeln!(out, "  {} -> computed_{:03X} [style=dotted, color=red];", from, last)
+                }
+                Exit::Return | Exit::Stop | Exit::Invalid => Ok(()),
+            }
+            .unwrap();
+        }
+    }
+
+    // Targets outside of the program image
+    let targets = graph.blocks.values().flat_map(|block| block.exits.iter()).filter_map(|exit| match *exit {
+        Exit::Fallthrough(target) | Exit::Jump(target) | Exit::Call(target) | Exit::Skip(target) => Some(target),
+        _ => None,
+    });
+
+    for target in targets.filter(|target| !graph.blocks.contains_key(target)).collect::<BTreeSet<_>>() {
+        writeln!(out, "  {} [label=\"{:03X} (outside the program)\", style=dashed];", node(target), target).unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Reconstructs the control-flow graph of a ROM, reports what static analysis cannot follow and writes the graph as
+/// DOT to `out` (standard output without one)
+pub fn run(rom: &Path, out: Option<&Path>, variant: Option<Variant>, load_address: Option<u16>, entry_point: Option<u16>) -> Result<(), String> {
+    let data = std::fs::read(rom).map_err(|e| format!("{}: {}", rom.display(), e))?;
+    let rom = loader::load(&data).map_err(|e| format!("Failed to load ROM: {}", e))?;
+
+    let mut config = Config::default();
+    rom.options.apply(&mut config);
+
+    loader::Options { variant, load_address, entry_point, ..Default::default() }.apply(&mut config);
+
+    // As `Core::with_config` does
+    config.detect_variant(&rom.program);
+
+    config.validate(rom.program.len()).map_err(|e| format!("Invalid configuration: {}", e))?;
+
+    let graph = flow::analyze(&rom.program, &config);
+
+    eprintln!("{} blocks in {} subroutines (including main)", graph.blocks.len(), graph.subroutines.len());
+
+    for addr in graph.computed_jumps.iter() {
+        eprintln!("Computed jump at {:03X}: its targets are not followed", addr);
+    }
+
+    for write in graph.code_writes.iter() {
+        eprintln!("Self-modifying write at {:03X}: stores to {:03X}-{:03X}, which holds code", write.at, write.target.start, write.target.end - 1);
+    }
+
+    let dot = dot(&graph, &rom.program, &config);
+
+    match out {
+        Some(path) => std::fs::write(path, dot).map_err(|e| format!("{}: {}", path.display(), e)),
+        None => {
+            print!("{}", dot);
+
+            Ok(())
+        }
+    }
+}
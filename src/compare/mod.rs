@@ -0,0 +1,91 @@
+use myuchip_core::{Core, config::Config, display::Display, loader, vip::Vip};
+
+use std::{fmt::Write as _, path::Path};
+
+/// Fixed RNG seed for the high-level core
+const SEED: u64 = 0xC8;
+
+/// Returns whether pixel (x, y) of the VIP raster is lit on the high-level display, stretched to the raster height
+fn hle_pixel(display: &Display, x: usize, y: usize) -> bool {
+    display[display.width() * (y * display.height() / Vip::RASTER_HEIGHT) + x] != 0
+}
+
+/// Draws both screens next to each other, one raster line in every four (one Chip-8 row)
+fn side_by_side(display: &Display, raster: &Display) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{:<width$}VIP", "HLE", width = Vip::RASTER_WIDTH + 2).unwrap();
+
+    for y in (0..Vip::RASTER_HEIGHT).step_by(4) {
+        let hle: String = (0..Vip::RASTER_WIDTH).map(|x| if hle_pixel(display, x, y) { '#' } else { '.' }).collect();
+        let vip: String = (0..Vip::RASTER_WIDTH).map(|x| if raster[Vip::RASTER_WIDTH * y + x] != 0 { '#' } else { '.' }).collect();
+
+        writeln!(out, "{}  {}", hle, vip).unwrap();
+    }
+
+    out
+}
+
+/// Runs a ROM on the high-level core and on an emulated COSMAC VIP for `frames` frames, comparing the screens after every
+/// frame; returns false if they ever differed
+///
+/// The VIP runs the given monitor ROM and Chip-8 interpreter images, raw or as hex listings (`conformance/vip` has
+/// stand-ins for both). The two run at different speeds and with different random numbers, so short-lived differences
+/// are expected; the first differing frame and the final screens are printed to tell them apart from real ones.
+pub fn run(rom: &Path, monitor: &Path, interpreter: &Path, frames: usize) -> Result<bool, String> {
+    let read = |path: &Path| std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e));
+
+    let rom = loader::load(&read(rom)?).map_err(|e| format!("Failed to load ROM: {}", e))?;
+
+    let mut config = Config::default();
+    rom.options.apply(&mut config);
+
+    if config.variant.display_size() != (Display::WIDTH, Display::HEIGHT) || config.load_address as usize != Vip::PROGRAM_START {
+        return Err("Only CHIP-8 programs loaded at 0x200 run on the VIP interpreter".into());
+    }
+
+    let mut core = Core::with_config(&rom.program, SEED, config);
+    let image = |path: &Path| Ok::<_, String>(loader::load(&read(path)?).map_err(|e| format!("{}: {}", path.display(), e))?.program);
+    let mut vip = Vip::new(&image(monitor)?, &image(interpreter)?, &rom.program).map_err(|e| format!("Failed to set up the VIP: {}", e))?;
+
+    let mut differing = 0;
+
+    for frame in 0..frames {
+        core.frame();
+        vip.frame();
+
+        let raster = vip.raster();
+
+        let matches = (0..Vip::RASTER_HEIGHT).all(|y| {
+            (0..Vip::RASTER_WIDTH).all(|x| hle_pixel(core.display(), x, y) == (raster[Vip::RASTER_WIDTH * y + x] != 0))
+        });
+
+        if !matches {
+            if differing == 0 {
+                print!("First difference in frame {}:\n{}\n", frame, side_by_side(core.display(), raster));
+            }
+
+            differing += 1;
+        }
+    }
+
+    if differing > 0 {
+        print!("After {} frames:\n{}\n", frames, side_by_side(core.display(), vip.raster()));
+    }
+
+    println!("{} of {} frames differed", differing, frames);
+
+    Ok(differing == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stub_interpreter() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("conformance/vip");
+
+        assert_eq!(run(&dir.join("draw.hex"), &dir.join("monitor.hex"), &dir.join("interpreter.hex"), 10), Ok(true));
+    }
+}
@@ -0,0 +1,311 @@
+use myuchip_core::{Core, bus::Address, config::{Config, Quirks}, display::Display, keypad::Keypad, loader};
+
+use std::{
+    fmt::Write as _,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+/// Fixed RNG seed so that ROMs using `rnd` render reproducibly
+const SEED: u64 = 0xC8;
+
+/// Screen region (in Chip-8 pixels) covered by a check, clipped to the display
+#[derive(Clone, Copy)]
+struct Region {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self { x: 0, y: 0, w: usize::MAX, h: usize::MAX }
+    }
+}
+
+/// Key held down from frame `from` up to (excluding) frame `to`
+struct Press {
+    from: usize,
+    to: usize,
+    key_index: usize,
+}
+
+/// A single manifest entry: run `rom` for `frames` frames, then hash `region` of the display
+struct Check {
+    suite: String,
+    name: String,
+    rom: PathBuf,
+    frames: usize,
+    pokes: Vec<(u16, u8)>,
+    presses: Vec<Press>,
+    region: Region,
+
+    /// Quirk profile from `Quirks::PROFILES` replacing the ROM's own settings
+    quirks: Option<Quirks>,
+    golden: Option<u64>,
+    /// Index of the line in the manifest, for blessing
+    line: usize,
+}
+
+enum Outcome {
+    Pass,
+    Fail(u64),
+    New(u64),
+    Missing,
+    Crash(String),
+}
+
+impl Outcome {
+    fn label(&self) -> String {
+        match self {
+            Outcome::Pass => "PASS".into(),
+            Outcome::Fail(hash) => format!("FAIL (got {:016x})", hash),
+            Outcome::New(hash) => format!("FAIL (no golden hash, got {:016x})", hash),
+            Outcome::Missing => "FAIL (ROM not found)".into(),
+            Outcome::Crash(msg) => format!("FAIL ({})", msg),
+        }
+    }
+}
+
+pub(crate) fn parse_num(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    parsed.map_err(|_| format!("invalid number `{}`", s))
+}
+
+/// Parses a manifest line of the form `<suite> <name> rom=... frames=... [poke=addr:val] [press=from-to:key] [region=x,y,w,h]
+/// [quirks=profile] hash=...`
+fn parse_check(line: &str, index: usize, base: &Path) -> Result<Check, String> {
+    let mut tokens = line.split_whitespace();
+
+    let (suite, name) = match (tokens.next(), tokens.next()) {
+        (Some(suite), Some(name)) => (suite.to_string(), name.to_string()),
+        _ => return Err("expected `<suite> <name>`".into()),
+    };
+
+    let mut check = Check {
+        suite,
+        name,
+        rom: PathBuf::new(),
+        frames: 0,
+        pokes: Vec::new(),
+        presses: Vec::new(),
+        region: Region::default(),
+        quirks: None,
+        golden: None,
+        line: index,
+    };
+
+    for token in tokens {
+        let (key, value) = token.split_once('=').ok_or_else(|| format!("expected `key=value`, got `{}`", token))?;
+
+        match key {
+            "rom" => check.rom = base.join(value),
+            "frames" => check.frames = parse_num(value)?,
+            "poke" => {
+                let (addr, data) = value.split_once(':').ok_or("expected `poke=addr:value`")?;
+
+                check.pokes.push((parse_num(addr)? as u16, parse_num(data)? as u8));
+            }
+            "press" => {
+                let (frames, key) = value.split_once(':').ok_or("expected `press=from-to:key`")?;
+                let (from, to) = frames.split_once('-').ok_or("expected `press=from-to:key`")?;
+
+                check.presses.push(Press {
+                    from: parse_num(from)?,
+                    to: parse_num(to)?,
+                    key_index: Keypad::grid_index(usize::from_str_radix(key, 16).map_err(|_| format!("invalid key `{}`", key))?),
+                });
+            }
+            "region" => {
+                let parts = value.split(',').map(parse_num).collect::<Result<Vec<_>, _>>()?;
+
+                if let [x, y, w, h] = parts[..] {
+                    check.region = Region { x, y, w, h };
+                } else {
+                    return Err("expected `region=x,y,w,h`".into());
+                }
+            }
+            "quirks" => {
+                let (_, quirks) = Quirks::PROFILES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(value))
+                    .ok_or_else(|| format!("unknown quirk profile `{}`", value))?;
+
+                check.quirks = Some(*quirks);
+            }
+            "hash" => {
+                check.golden = match value {
+                    "-" => None,
+                    _ => Some(u64::from_str_radix(value, 16).map_err(|_| format!("invalid hash `{}`", value))?),
+                };
+            }
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+    }
+
+    if check.frames == 0 {
+        return Err("missing `frames`".into());
+    }
+
+    Ok(check)
+}
+
+/// FNV-1a hash of the lit/unlit state of every pixel in a region
+fn hash_region(display: &Display, region: Region) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+
+    for y in region.y..usize::min(region.y.saturating_add(region.h), display.height()) {
+        for x in region.x..usize::min(region.x.saturating_add(region.w), display.width()) {
+            hash ^= (display[display.width() * y + x] != 0) as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+
+    hash
+}
+
+fn run_check(check: &Check) -> Outcome {
+    let rom = match std::fs::read(&check.rom) {
+        Ok(data) => match loader::load(&data) {
+            Ok(rom) => rom,
+            Err(e) => return Outcome::Crash(e.to_string()),
+        },
+        Err(_) => return Outcome::Missing,
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut config = Config::default();
+        rom.options.apply(&mut config);
+
+        if let Some(quirks) = check.quirks {
+            config.quirks = quirks;
+        }
+
+        let mut core = Core::with_config(&rom.program, SEED, config);
+
+        for &(addr, data) in check.pokes.iter() {
+            core.bus_mut().write_byte(Address::new(addr), data);
+        }
+
+        for frame in 0..check.frames {
+            let held = check.presses.iter().filter(|press| (press.from..press.to).contains(&frame)).map(|press| press.key_index);
+
+            core.keypad_mut().update_state(held);
+            core.frame();
+        }
+
+        hash_region(core.display(), check.region)
+    }));
+
+    match (result, check.golden) {
+        (Ok(hash), Some(golden)) if hash == golden => Outcome::Pass,
+        (Ok(hash), Some(_)) => Outcome::Fail(hash),
+        (Ok(hash), None) => Outcome::New(hash),
+        (Err(payload), _) => {
+            let msg = payload.downcast_ref::<String>().cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|msg| msg.to_string()))
+                .unwrap_or_else(|| "panicked".into());
+
+            Outcome::Crash(msg)
+        }
+    }
+}
+
+/// Runs every check in the manifest and prints a pass/fail table; returns false if any check failed, has no ROM or has no
+/// golden hash
+///
+/// With `bless`, the golden hash of every check that ran is replaced with the current result, and checks without one
+/// do not count as failures.
+pub fn run(manifest: &Path, filter: Option<&str>, bless: bool) -> Result<bool, String> {
+    let text = std::fs::read_to_string(manifest).map_err(|e| format!("{}: {}", manifest.display(), e))?;
+    let base = manifest.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let mut checks = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let check = parse_check(line, index, base).map_err(|e| format!("{}:{}: {}", manifest.display(), index + 1, e))?;
+
+        if filter.is_none_or(|filter| check.suite.contains(filter) || check.name.contains(filter)) {
+            checks.push(check);
+        }
+    }
+
+    // Silence the default panic message; crashes are reported in the table instead
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let outcomes: Vec<Outcome> = checks.iter().map(run_check).collect();
+
+    panic::set_hook(hook);
+
+    let suite_width = checks.iter().map(|check| check.suite.len()).max().unwrap_or(0).max("SUITE".len());
+    let name_width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0).max("CHECK".len());
+
+    let mut table = String::new();
+
+    writeln!(table, "{:suite_width$}  {:name_width$}  RESULT", "SUITE", "CHECK").unwrap();
+
+    for (check, outcome) in checks.iter().zip(outcomes.iter()) {
+        writeln!(table, "{:suite_width$}  {:name_width$}  {}", check.suite, check.name, outcome.label()).unwrap();
+    }
+
+    let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|outcome| f(outcome)).count();
+
+    let (passed, failed, new, missing) = (
+        count(|outcome| matches!(outcome, Outcome::Pass)),
+        count(|outcome| matches!(outcome, Outcome::Fail(_) | Outcome::Crash(_))),
+        count(|outcome| matches!(outcome, Outcome::New(_))),
+        count(|outcome| matches!(outcome, Outcome::Missing)),
+    );
+
+    writeln!(table, "\n{} passed, {} failed, {} without golden hash, {} without ROM", passed, failed, new, missing).unwrap();
+
+    if missing > 0 {
+        writeln!(table, "Checks without their ROM fail; see conformance/roms/README.md for where to get them").unwrap();
+    }
+
+    if new > 0 && !bless {
+        writeln!(table, "Checks without a golden hash fail; record them with --bless against a known-good build").unwrap();
+    }
+
+    print!("{}", table);
+
+    if bless {
+        for (check, outcome) in checks.iter().zip(outcomes.iter()) {
+            let hash = match outcome {
+                Outcome::Fail(hash) | Outcome::New(hash) => *hash,
+                _ => continue,
+            };
+
+            // Only the hash token is rewritten so that the manifest keeps its alignment
+            let line = &mut lines[check.line];
+
+            match line.find("hash=") {
+                Some(start) => {
+                    let end = line[start..].find(char::is_whitespace).map_or(line.len(), |len| start + len);
+
+                    line.replace_range(start..end, &format!("hash={:016x}", hash));
+                }
+                None => line.push_str(&format!(" hash={:016x}", hash)),
+            }
+        }
+
+        std::fs::write(manifest, lines.join("\n") + "\n").map_err(|e| format!("{}: {}", manifest.display(), e))?;
+
+        println!("Blessed {}", manifest.display());
+    }
+
+    Ok(failed == 0 && missing == 0 && (new == 0 || bless))
+}
@@ -0,0 +1,87 @@
+use minifb::{Key, KeyRepeat, Window};
+
+/// Runtime emulation speed controls
+///
+/// - `P` toggles pause
+/// - `N` advances exactly one frame while paused
+/// - `Tab` (held) fast-forwards
+/// - `M` toggles slow motion
+pub struct Controls {
+    paused: bool,
+    slow_motion: bool,
+
+    /// Frames emulated per window update while fast-forwarding
+    fast_forward: usize,
+
+    /// Window updates per emulated frame in slow motion
+    slow_motion_divisor: usize,
+    slow_motion_counter: usize,
+}
+
+impl Controls {
+    pub const PAUSE: Key = Key::P;
+    pub const FRAME_ADVANCE: Key = Key::N;
+    pub const FAST_FORWARD: Key = Key::Tab;
+    pub const SLOW_MOTION: Key = Key::M;
+
+    pub fn new(fast_forward: usize, slow_motion_divisor: usize) -> Self {
+        Self {
+            paused: false,
+            slow_motion: false,
+            fast_forward: fast_forward.max(1),
+            slow_motion_divisor: slow_motion_divisor.max(1),
+            slow_motion_counter: 0,
+        }
+    }
+
+    /// Polls the control keys and returns how many frames to emulate before the next window update
+    pub fn frames_to_run(&mut self, window: &Window) -> usize {
+        if window.is_key_pressed(Self::PAUSE, KeyRepeat::No) {
+            self.paused = !self.paused;
+        }
+
+        if window.is_key_pressed(Self::SLOW_MOTION, KeyRepeat::No) {
+            self.slow_motion = !self.slow_motion;
+            self.slow_motion_counter = 0;
+        }
+
+        if self.paused {
+            return window.is_key_pressed(Self::FRAME_ADVANCE, KeyRepeat::Yes) as usize;
+        }
+
+        if window.is_key_down(Self::FAST_FORWARD) {
+            return self.fast_forward;
+        }
+
+        if self.slow_motion {
+            self.slow_motion_counter = (self.slow_motion_counter + 1) % self.slow_motion_divisor;
+
+            return (self.slow_motion_counter == 0) as usize;
+        }
+
+        1
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Window title reflecting the current state
+    pub fn title(&self, window: &Window) -> String {
+        let state = if self.paused {
+            " [paused]".to_string()
+        } else if window.is_key_down(Self::FAST_FORWARD) {
+            format!(" [x{}]", self.fast_forward)
+        } else if self.slow_motion {
+            format!(" [1/{}]", self.slow_motion_divisor)
+        } else {
+            String::new()
+        };
+
+        format!("myuchip{}", state)
+    }
+}
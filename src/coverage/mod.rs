@@ -0,0 +1,120 @@
+use myuchip_core::{Core, bus::{Address, coverage::Coverage}, disasm};
+
+use std::{fmt::Write as _, ops::Range, path::Path};
+
+/// Runs of consecutive addresses for which `test` holds
+fn ranges(len: usize, test: impl Fn(usize) -> bool) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for addr in (0..len).filter(|&addr| test(addr)) {
+        match ranges.last_mut() {
+            Some(range) if range.end == addr => range.end += 1,
+            _ => ranges.push(addr..addr + 1),
+        }
+    }
+
+    ranges
+}
+
+/// Usage flags of a byte: `x` fetched as code, `r` read as data, `w` written
+fn flags(coverage: &Coverage, addr: usize) -> String {
+    [(coverage.was_fetched(addr), 'x'), (coverage.was_read(addr), 'r'), (coverage.was_written(addr), 'w')]
+        .iter()
+        .map(|&(set, flag)| if set { flag } else { '-' })
+        .collect()
+}
+
+fn is_reached(coverage: &Coverage, addr: usize) -> bool {
+    coverage.was_fetched(addr) || coverage.was_read(addr) || coverage.was_written(addr)
+}
+
+/// Writes the coverage as JSON if `path` ends in `.json`, as an annotated listing of `program` otherwise
+pub fn write(core: &Core, program: Range<usize>, path: &Path) -> Result<(), String> {
+    let coverage = core.bus().coverage().ok_or("coverage is not enabled")?;
+
+    let text = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => json(coverage, program),
+        _ => listing(core, coverage, program),
+    };
+
+    std::fs::write(path, text).map_err(|e| e.to_string())
+}
+
+/// Executed, read and written address ranges (end exclusive) plus a summary of the program bytes
+fn json(coverage: &Coverage, program: Range<usize>) -> String {
+    let list = |test: &dyn Fn(usize) -> bool| {
+        ranges(coverage.len(), test).iter().map(|range| format!("[{}, {}]", range.start, range.end)).collect::<Vec<_>>().join(", ")
+    };
+
+    let reached = program.clone().filter(|&addr| is_reached(coverage, addr)).count();
+
+    let mut out = String::from("{\n");
+
+    let _ = writeln!(out, "  \"program\": {{ \"start\": {}, \"end\": {}, \"reached\": {} }},", program.start, program.end, reached);
+    let _ = writeln!(out, "  \"executed\": [{}],", list(&|addr| coverage.was_executed(addr)));
+    let _ = writeln!(out, "  \"fetched\": [{}],", list(&|addr| coverage.was_fetched(addr)));
+    let _ = writeln!(out, "  \"read\": [{}],", list(&|addr| coverage.was_read(addr)));
+    let _ = writeln!(out, "  \"written\": [{}]", list(&|addr| coverage.was_written(addr)));
+
+    out.push_str("}\n");
+    out
+}
+
+/// Disassembly of the program marked with how each instruction or byte was used
+///
+/// Executed instructions are disassembled where they started, bytes used as data are listed one by one, and pairs of
+/// bytes nothing touched are disassembled and marked `!` so untested code paths stand out.
+fn listing(core: &Core, coverage: &Coverage, program: Range<usize>) -> String {
+    let variant = core.config().variant;
+    let byte = |addr: usize| core.bus().peek_byte(Address::new(addr as u32));
+
+    let reached = program.clone().filter(|&addr| is_reached(coverage, addr)).count();
+
+    let mut out = String::new();
+
+    let _ = writeln!(out, "; {} of {} program bytes reached", reached, program.len());
+    let _ = writeln!(out, "; x = fetched as code, r = read as data, w = written, ! = never reached");
+
+    let mut addr = program.start;
+
+    while addr < program.end {
+        let next = addr + 1;
+
+        if coverage.was_executed(addr) || (!is_reached(coverage, addr) && next < program.end && !is_reached(coverage, next)) {
+            let opcode = core.bus().read_word(Address::new(addr as u32));
+            let mark = if coverage.was_executed(addr) { ' ' } else { '!' };
+
+            let _ = writeln!(out, "{:03X}  {:04X}  {}{}  {}", addr, opcode, flags(coverage, addr), mark, disasm::disassemble(opcode, variant));
+
+            addr += 2;
+        } else {
+            let mark = if is_reached(coverage, addr) { ' ' } else { '!' };
+
+            let _ = writeln!(out, "{:03X}  {:02X}    {}{}  DB {:02X}", addr, byte(addr), flags(coverage, addr), mark, byte(addr));
+
+            addr += 1;
+        }
+    }
+
+    // Runs of bytes used the same way, e.g. the font or the stack
+    let mut outside: Vec<(Range<usize>, String)> = Vec::new();
+
+    for addr in (0..coverage.len()).filter(|&addr| !program.contains(&addr) && is_reached(coverage, addr)) {
+        let flags = flags(coverage, addr);
+
+        match outside.last_mut() {
+            Some((range, last)) if range.end == addr && *last == flags => range.end += 1,
+            _ => outside.push((addr..addr + 1, flags)),
+        }
+    }
+
+    if !outside.is_empty() {
+        let _ = writeln!(out, "\n; Used outside of the program");
+
+        for (range, flags) in outside {
+            let _ = writeln!(out, "; {:03X}-{:03X}  {}", range.start, range.end - 1, flags);
+        }
+    }
+
+    out
+}
@@ -1,30 +1,9 @@
 use minifb::Key;
 
-struct Keymap;
+/// Maps host keys onto the 4x4 Chip-8 keypad grid
+pub struct Keymap;
 
 impl Keymap {
-    pub fn cpu_index(key_index: usize) -> u8 {
-        const CPU_INDICES: [u8; Keypad::NUM] = [
-            0x1, 0x2, 0x3, 0xC,
-            0x4, 0x5, 0x6, 0xD,
-            0x7, 0x8, 0x9, 0xE,
-            0xA, 0x0, 0xB, 0xF,
-        ];
-
-        CPU_INDICES[key_index]
-    }
-
-    pub fn key_index(cpu_index: usize) -> usize {
-        const KEY_INDICES: [usize; Keypad::NUM] = [
-            0xD, 0x0, 0x1, 0x2,
-            0x4, 0x5, 0x6, 0x8,
-            0x9, 0xA, 0xC, 0xE,
-            0x3, 0x7, 0xB, 0xF,
-        ];
-
-        KEY_INDICES[cpu_index]
-    }
-
     pub fn from_key(key: &Key) -> Option<usize> {
         let key_index: usize = match key {
             Key::Key1 => 0,
@@ -52,46 +31,29 @@ impl Keymap {
             Some(key_index)
         }
     }
-}
-
-type KeyState = [bool; Keypad::NUM];
-
-pub struct Keypad {
-    state: KeyState,
-}
-
-impl Keypad {
-    const NUM: usize = 16;
 
-    pub fn is_key_pressed(&self, cpu_index: usize) -> bool {
-        self.state[Keymap::key_index(cpu_index % Self::NUM)]
-    }
-
-    /// Returns Some if any key is pressed (key with the lowest index is returned)
-    pub fn any_key(&self) -> Option<u8> {
-        for key_index in 0..Self::NUM {
-            if self.state[key_index] {
-                return Some(Keymap::cpu_index(key_index));
-            }
-        }
-    
-        None
-    }
-
-    /// Fills key state from window key state
-    pub fn update_state(&mut self, state: Vec<Key>) {
-        self.state.fill(false);
-
-        state.iter().for_each(|key| {
-            if let Some(key_index) = Keymap::from_key(key) {
-                self.state[key_index] = true;
-            }
-        });
-    }
-}
+    /// Bindings of the second (CHIP-8X) keypad, on the numeric keypad
+    pub fn from_second_key(key: &Key) -> Option<usize> {
+        let key_index: usize = match key {
+            Key::NumPad7 => 0,
+            Key::NumPad8 => 1,
+            Key::NumPad9 => 2,
+            Key::NumPadSlash => 3,
+            Key::NumPad4 => 4,
+            Key::NumPad5 => 5,
+            Key::NumPad6 => 6,
+            Key::NumPadAsterisk => 7,
+            Key::NumPad1 => 8,
+            Key::NumPad2 => 9,
+            Key::NumPad3 => 10,
+            Key::NumPadMinus => 11,
+            Key::NumPad0 => 12,
+            Key::NumPadDot => 13,
+            Key::NumPadEnter => 14,
+            Key::NumPadPlus => 15,
+            _ => return None,
+        };
 
-impl Default for Keypad {
-    fn default() -> Self {
-        Self { state: [false; Self::NUM] }
+        Some(key_index)
     }
 }
@@ -1,106 +1,603 @@
-use crate::{
-    bus::{Bus, memory::Memory},
-    cpu::{Cpu, CpuEvent},
-    display::*,
-    keypad::Keypad,
-};
+use crate::{capture::{AvRecorder, GifRecorder}, controls::Controls, keypad::Keymap, memview::MemoryViewer, romdb::RomDb, rpl::FlagStore, watch::RomWatcher};
 
-use std::{rc::Rc, cell::RefCell};
+use myuchip_core::{Core, bus::{OnViolation, Violation, device::OnWrite}, config::{Config, RplFlags, Variant}, font::Font, loader};
 
-pub use clap::Parser;
+use std::{collections::HashSet, ops::Range, path::PathBuf};
+
+pub use clap::{Parser, Subcommand};
 pub use minifb::{Key, KeyRepeat, Window, WindowOptions};
 
-mod bus;
-mod cpu;
-mod display;
+mod capture;
+pub mod cfg;
+pub mod compare;
+pub mod conformance;
+mod controls;
+mod coverage;
 mod keypad;
+mod lint;
+mod memview;
+mod profile;
+mod romdb;
+mod rpl;
+mod watch;
 
 #[derive(Parser, Debug, Default)]
-#[command(version, about)]
+#[command(version, about, subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to Chip-8 ROM
-    rom_path: String,
+    #[arg(required = true)]
+    rom_path: Option<String>,
+
+    /// Speed multiplier while fast-forward (Tab) is held
+    #[arg(long, default_value_t = 4)]
+    fast_forward: usize,
+
+    /// Slowdown factor when slow motion (M) is toggled on
+    #[arg(long, default_value_t = 4)]
+    slow_motion: usize,
+
+    /// Record every frame to <RECORD>.y4m and the beeper to <RECORD>.wav
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Run without a window, as fast as possible
+    #[arg(long, requires = "frames")]
+    headless: bool,
+
+    /// Stop after this many frames
+    #[arg(long)]
+    frames: Option<usize>,
+
+    /// Open a live memory viewer and hex editor window
+    #[arg(long)]
+    memory_viewer: bool,
+
+    /// Reload the ROM and reset the machine whenever the file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// With --watch, only patch the program bytes and keep the machine state
+    #[arg(long, requires = "watch")]
+    hot_patch: bool,
+
+    /// Per-ROM settings database, skipped if the file does not exist
+    #[arg(long, default_value = "romdb.txt")]
+    rom_db: PathBuf,
+
+    /// Chip-8 dialect (chip8, chip8e, chip8i, chip8x, hires, megachip); HIRES programs are recognized without one
+    #[arg(long, value_parser = romdb::parse_variant)]
+    variant: Option<Variant>,
+
+    /// Address the ROM is loaded at (0x600 for ETI 660 programs)
+    #[arg(long, value_parser = romdb::parse_address)]
+    load_address: Option<u16>,
+
+    /// Initial PC, defaults to the load address
+    #[arg(long, value_parser = romdb::parse_address)]
+    entry_point: Option<u16>,
+
+    /// Keep the call stack at 0xEA0 and the display at 0xF00 in memory, as the VIP interpreter does
+    #[arg(long)]
+    vip_memory: bool,
+
+    /// Font (vip, eti660, dream6800, fishnchips, octo) or the path of a font binary: 80 bytes of 4x5 glyphs, optionally
+    /// followed by 8x10 ones
+    #[arg(long, value_parser = parse_font)]
+    font: Option<Font>,
+
+    /// Address the font is copied to (0x050 by default; some programs read it at 0x000)
+    #[arg(long, value_parser = romdb::parse_address)]
+    font_address: Option<u16>,
+
+    /// Enable the SUPER-CHIP Fx75/Fx85 RPL user flags (schip for 8, xochip for 16); they are saved per ROM in the user
+    /// data directory and restored on the next run
+    #[arg(long, value_parser = romdb::parse_rpl_flags)]
+    rpl_flags: Option<RplFlags>,
+
+    /// Make addresses START-END (inclusive) read-only; writes pause with a fault, or are dropped with `:ignore`
+    #[arg(long, value_parser = parse_read_only)]
+    read_only: Vec<(Range<u32>, OnWrite)>,
+
+    /// Count executions per address, instruction and subroutine, print the hot spots on exit and write a collapsed-stack
+    /// flame graph to <PROFILE>
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// Record which bytes are executed, read and written, and write them on exit to <COVERAGE> (JSON if it ends in
+    /// `.json`, an annotated listing otherwise)
+    #[arg(long)]
+    coverage: Option<PathBuf>,
+
+    /// Report instructions whose result depends on a quirk setting on exit, and the quirk profiles that fit
+    #[arg(long)]
+    lint_quirks: bool,
+
+    /// Report accesses past the end of memory, reads of memory never written and writes below 0x200: pause on the
+    /// first one, or only print them with `--strict=log`
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "fault", value_parser = parse_strict)]
+    strict: Option<OnViolation>,
 }
 
-pub struct Core {
-    cpu: Cpu,
-    display: Rc<RefCell<Display>>,
-    keypad: Rc<RefCell<Keypad>>,
+/// Parses a font name, or reads a font binary
+fn parse_font(value: &str) -> Result<Font, String> {
+    if let Ok(font) = romdb::parse_font(value) {
+        return Ok(font);
+    }
+
+    let data = std::fs::read(value).map_err(|e| format!("not a font name, and {}: {}", value, e))?;
+
+    Font::from_binary(&data).map_err(|e| format!("{}: {}", value, e))
+}
+
+/// Parses `fault` or `log`
+fn parse_strict(value: &str) -> Result<OnViolation, String> {
+    match value {
+        "fault" => Ok(OnViolation::Fault),
+        "log" => Ok(OnViolation::Log),
+        _ => Err(format!("unknown strict mode `{}`, expected `fault` or `log`", value)),
+    }
 }
 
-impl Core {
-    const SPRITES_START: usize = 0x50;
-    const ROM_START: usize = 0x200;
-
-    const SPRITE_SIZE: usize = 5;
-    const NUM_SPRITES: usize = 16;
-
-    const SPRITES_SIZE: usize = Self::SPRITE_SIZE * Self::NUM_SPRITES;
-    const MAX_ROM_SIZE: usize = Memory::SIZE - Self::ROM_START;
-
-    const SPRITES: [u8; Self::SPRITES_SIZE] = [
-        0xF0, 0x90, 0x90, 0x90, 0xF0,
-        0x20, 0x60, 0x20, 0x20, 0x70,
-        0xF0, 0x10, 0xF0, 0x80, 0xF0,
-        0xF0, 0x10, 0xF0, 0x10, 0xF0,
-        0x90, 0x90, 0xF0, 0x10, 0x10,
-        0xF0, 0x80, 0xF0, 0x10, 0xF0,
-        0xF0, 0x80, 0xF0, 0x90, 0xF0,
-        0xF0, 0x10, 0x20, 0x40, 0x40,
-        0xF0, 0x90, 0xF0, 0x90, 0xF0,
-        0xF0, 0x90, 0xF0, 0x10, 0xF0,
-        0xF0, 0x90, 0xF0, 0x90, 0x90,
-        0xE0, 0x90, 0xE0, 0x90, 0xE0,
-        0xF0, 0x80, 0x80, 0x80, 0xF0,
-        0xE0, 0x90, 0x90, 0x90, 0xE0,
-        0xF0, 0x80, 0xF0, 0x80, 0xF0,
-        0xF0, 0x80, 0xF0, 0x80, 0x80,
-    ];
+/// Parses `START-END[:ignore]`
+fn parse_read_only(value: &str) -> Result<(Range<u32>, OnWrite), String> {
+    let (range, on_write) = match value.split_once(':') {
+        Some((range, "ignore")) => (range, OnWrite::Ignore),
+        Some((_, policy)) => return Err(format!("unknown write policy `{}`, expected `ignore`", policy)),
+        None => (value, OnWrite::Fault),
+    };
+
+    let (start, end) = range.split_once('-').ok_or("expected `START-END[:ignore]`")?;
+
+    Ok((romdb::parse_address(start)? as u32..romdb::parse_address(end)? as u32 + 1, on_write))
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the conformance test ROMs headlessly and compare against golden hashes
+    Test {
+        /// Path to the suite manifest
+        #[arg(long, default_value = "conformance/suite.txt")]
+        manifest: PathBuf,
+
+        /// Only run checks whose suite or name contains this string
+        filter: Option<String>,
+
+        /// Record the current results as the new golden hashes
+        #[arg(long)]
+        bless: bool,
+    },
+
+    /// Run a ROM on the emulated COSMAC VIP (original interpreter) next to the high-level core and compare the screens
+    Compare {
+        /// Path to Chip-8 ROM
+        rom: PathBuf,
+
+        /// VIP monitor ROM image (512 bytes, mapped at 0x8000)
+        #[arg(long)]
+        monitor: PathBuf,
+
+        /// Chip-8 interpreter image, loaded at 0x000
+        #[arg(long)]
+        interpreter: PathBuf,
+
+        /// Number of frames to compare
+        #[arg(long, default_value_t = 600)]
+        frames: usize,
+    },
+
+    /// Reconstruct a ROM's control-flow graph without running it and write it as Graphviz DOT
+    Cfg {
+        /// Path to Chip-8 ROM
+        rom: PathBuf,
+
+        /// Output file, standard output by default
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        /// Chip-8 dialect (chip8, chip8e, chip8i, chip8x, hires, megachip)
+        #[arg(long, value_parser = romdb::parse_variant)]
+        variant: Option<Variant>,
+
+        /// Address the ROM is loaded at
+        #[arg(long, value_parser = romdb::parse_address)]
+        load_address: Option<u16>,
+
+        /// Initial PC, defaults to the load address
+        #[arg(long, value_parser = romdb::parse_address)]
+        entry_point: Option<u16>,
+    },
+}
+
+/// Desktop frontend (window, input and frame pacing) around the emulation core
+pub struct Frontend {
+    core: Core,
+
+    /// Largest display of the variant; smaller displays are stretched to fill it
+    screen: (usize, usize),
+
+    /// Display rendered in the configured palette
+    display_buffer: Vec<u32>,
+
+    /// `display_buffer` stretched to the screen size
+    buffer: Vec<u32>,
+    controls: Controls,
+    recorder: Option<GifRecorder>,
+    av_recorder: Option<AvRecorder>,
+    headless: bool,
+    frames: Option<usize>,
+    memory_viewer: bool,
+    watcher: Option<RomWatcher>,
+    hot_patch: bool,
+
+    /// Flame graph output path while profiling
+    profile: Option<PathBuf>,
+
+    /// Coverage output path and the program's addresses
+    coverage: Option<(PathBuf, Range<usize>)>,
+
+    /// Strict mode reports printed so far, each printed once
+    violations: HashSet<Violation>,
+
+    /// Where the RPL user flags are saved whenever Fx75 changes them
+    user_flags: Option<FlagStore>,
+}
+
+impl Frontend {
+    /// Window width in pixels, before any desktop scaling
+    const WINDOW_WIDTH: usize = 512;
+
+    const SCREENSHOT: Key = Key::F2;
+    const RECORD: Key = Key::F3;
 
     pub fn new(args: Args) -> Self {
-        // Load ROM
-        let mut mem = Memory::default();
+        let rom_path = PathBuf::from(args.rom_path.expect("No ROM path given"));
+        let data = std::fs::read(&rom_path).expect("Failed to read ROM");
+
+        let rom = loader::load(&data).unwrap_or_else(|e| panic!("Failed to load ROM: {}", e));
+
+        let rom_db = RomDb::load(&args.rom_db).unwrap_or_else(|e| panic!("Failed to read ROM database: {}", e));
+
+        // Container options first, then the database entry, then the command line
+        let mut config = Config::default();
+        rom.options.apply(&mut config);
+
+        // The hash is what a database entry for this ROM starts with
+        let hash = romdb::hash(&rom.program);
+
+        match rom_db.lookup(&rom.program) {
+            Some(options) => {
+                eprintln!("ROM hash {:016x} (settings from {})", hash, args.rom_db.display());
+
+                options.apply(&mut config);
+            }
+            None => eprintln!("ROM hash {:016x}", hash),
+        }
+
+        loader::Options {
+            variant: args.variant,
+            load_address: args.load_address,
+            entry_point: args.entry_point,
+            vip_memory_map: if args.vip_memory { Some(true) } else { None },
+            font: args.font,
+            font_address: args.font_address,
+            rpl_flags: args.rpl_flags,
+            ..Default::default()
+        }.apply(&mut config);
+
+        config.validate(rom.program.len()).unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+
+        let mut core = Core::with_config(&rom.program, rand::random(), config);
 
-        let rom = std::fs::read(args.rom_path).expect("Failed to read ROM");
-        let len = usize::min(rom.len(), Self::MAX_ROM_SIZE);
+        for (range, on_write) in args.read_only {
+            core.bus_mut().protect(range, on_write);
+        }
+
+        if args.profile.is_some() {
+            core.enable_profiler();
+        }
+
+        if args.coverage.is_some() {
+            core.bus_mut().enable_coverage();
+        }
+
+        if args.lint_quirks {
+            core.enable_quirk_linter();
+        }
+
+        if let Some(on_violation) = args.strict {
+            core.enable_strict(on_violation);
+        }
+
+        let user_flags = Self::open_user_flags(&mut core, &rom.program);
+
+        let load_address = core.config().load_address as usize;
+        let program = load_address..load_address + usize::min(rom.program.len(), core.config().max_rom_size());
 
-        mem[Self::SPRITES_START..Self::SPRITES_START + Self::SPRITES_SIZE].copy_from_slice(&Self::SPRITES[..]);
-        mem[Self::ROM_START..Self::ROM_START + len].copy_from_slice(&rom[..len]);
+        let (width, height) = core.config().variant.screen_size();
 
-        let display = Rc::new(RefCell::new(Display::default()));
-        let keypad = Rc::new(RefCell::new(Keypad::default()));
+        let av_recorder = args.record.map(|stem| {
+            AvRecorder::new(&stem, width, height, Self::scale(width)).expect("Failed to start recording")
+        });
 
         Self {
-            cpu: Cpu::new(Bus::new(mem), display.clone(), keypad.clone()),
-            display,
-            keypad,
+            core,
+            screen: (width, height),
+            display_buffer: Vec::new(),
+            buffer: vec![0; width * height],
+            watcher: if args.watch { Some(RomWatcher::new(rom_path, data)) } else { None },
+            hot_patch: args.hot_patch,
+            controls: Controls::new(args.fast_forward, args.slow_motion),
+            recorder: None,
+            av_recorder,
+            headless: args.headless,
+            frames: args.frames,
+            memory_viewer: args.memory_viewer,
+            profile: args.profile,
+            coverage: args.coverage.map(|path| (path, program)),
+            violations: HashSet::new(),
+            user_flags,
+        }
+    }
+
+    /// Window pixels per screen pixel
+    fn scale(width: usize) -> usize {
+        usize::max(1, Self::WINDOW_WIDTH / width)
+    }
+
+    /// Renders the display and stretches it to the screen size
+    fn render(&mut self) {
+        let (width, height) = (self.core.display().width(), self.core.display().height());
+        let (screen_width, screen_height) = self.screen;
+
+        self.display_buffer.resize(width * height, 0);
+        self.core.render(&mut self.display_buffer);
+
+        for (y, row) in self.buffer.chunks_exact_mut(screen_width).enumerate() {
+            let src = &self.display_buffer[width * (y * height / screen_height)..][..width];
+
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = src[x * width / screen_width];
+            }
+        }
+    }
+
+    /// Restores the RPL user flags saved for `program`, if the variant has any, and returns where to save them
+    fn open_user_flags(core: &mut Core, program: &[u8]) -> Option<FlagStore> {
+        if core.config().rpl_flags == RplFlags::None {
+            return None;
+        }
+
+        FlagStore::open(program, core.user_flags_mut()).map_err(|e| eprintln!("RPL flags will not be kept: {}", e)).ok()
+    }
+
+    /// Reloads the ROM if it changed on disk
+    fn poll_rom(&mut self) {
+        if let Some(data) = self.watcher.as_mut().and_then(RomWatcher::poll) {
+            match loader::load(data) {
+                Ok(rom) if rom.program.len() > self.core.config().max_rom_size() => {
+                    eprintln!("Failed to reload ROM: {} bytes do not fit after the load address", rom.program.len());
+                }
+                Ok(rom) => {
+                    self.core.reload(&rom.program, self.hot_patch);
+
+                    // The flags are kept per program, and the new one may not be the old one
+                    if self.user_flags.is_some() {
+                        self.core.user_flags_mut().fill(0);
+                        self.user_flags = Self::open_user_flags(&mut self.core, &rom.program);
+                    }
+
+                    eprintln!("Reloaded ROM ({} bytes)", rom.program.len());
+                }
+                Err(e) => eprintln!("Failed to reload ROM: {}", e),
+            }
+        }
+    }
+
+    /// Emulates one frame and feeds it to the active recorders; a bus fault pauses emulation
+    fn frame(&mut self) {
+        self.core.frame();
+
+        if let Some(fault) = self.core.take_fault() {
+            eprintln!("Bus fault before PC {:03X}: {}", self.core.regfile().pc, fault);
+
+            self.controls.pause();
+        }
+
+        for violation in self.core.take_violations() {
+            if self.violations.insert(violation) {
+                eprintln!("Strict mode at {:03X}: {}", violation.at, violation.fault);
+            }
+        }
+
+        if let Some(store) = &mut self.user_flags {
+            if let Err(e) = store.save(self.core.user_flags()) {
+                eprintln!("Failed to save RPL flags: {}", e);
+
+                self.user_flags = None;
+            }
+        }
+
+        self.render();
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.push(&self.buffer) {
+                eprintln!("Failed to record frame: {}", e);
+
+                self.recorder = None;
+            }
+        }
+
+        if let Some(av_recorder) = &mut self.av_recorder {
+            let core = &self.core;
+
+            if let Err(e) = av_recorder.push(&self.buffer, core.is_sound_active(), |out, rate| core.mix_digital_sound(out, rate)) {
+                eprintln!("Failed to record frame: {}", e);
+
+                self.av_recorder = None;
+            }
+        }
+
+        if let Some(frames) = &mut self.frames {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.frames == Some(0)
+    }
+
+    /// Closes the recorders and writes the profile, coverage and quirk reports
+    fn finish(&mut self) {
+        if self.recorder.is_some() {
+            self.toggle_recording();
+        }
+
+        if let Some(av_recorder) = self.av_recorder.take() {
+            if let Err(e) = av_recorder.finish() {
+                eprintln!("Failed to finish recording: {}", e);
+            }
+        }
+
+        if let (Some(path), Some(profiler)) = (&self.profile, self.core.profiler()) {
+            profile::print_report(profiler);
+
+            match profile::write_flame_graph(profiler, path) {
+                Ok(()) => eprintln!("\nWrote flame graph stacks to {}", path.display()),
+                Err(e) => eprintln!("Failed to write flame graph: {}", e),
+            }
+        }
+
+        if let Some(linter) = self.core.quirk_linter() {
+            lint::print_report(linter, &self.core.config().quirks);
+        }
+
+        if let Some((path, program)) = &self.coverage {
+            match coverage::write(&self.core, program.clone(), path) {
+                Ok(()) => eprintln!("Wrote coverage to {}", path.display()),
+                Err(e) => eprintln!("Failed to write coverage: {}", e),
+            }
+        }
+    }
+
+    /// Saves the display as a PNG at window size
+    fn screenshot(&self) {
+        let path = capture::capture_path("png");
+        let (width, height) = self.screen;
+
+        match capture::screenshot(&path, &self.buffer, width, height, Self::scale(width)) {
+            Ok(()) => eprintln!("Saved screenshot to {}", path.display()),
+            Err(e) => eprintln!("Failed to save screenshot: {}", e),
+        }
+    }
+
+    /// Starts or stops recording frames into a GIF
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => match recorder.finish() {
+                Ok(()) => eprintln!("Stopped recording"),
+                Err(e) => eprintln!("Failed to finish recording: {}", e),
+            },
+            None => {
+                let path = capture::capture_path("gif");
+                let (width, height) = self.screen;
+
+                match GifRecorder::new(&path, width, height, Self::scale(width)) {
+                    Ok(recorder) => {
+                        eprintln!("Recording to {}", path.display());
+
+                        self.recorder = Some(recorder);
+                    }
+                    Err(e) => eprintln!("Failed to start recording: {}", e),
+                }
+            }
         }
     }
 
     pub fn run(&mut self) {
+        if self.headless {
+            while !self.is_done() {
+                self.frame();
+            }
+
+            self.finish();
+
+            return;
+        }
+
+        let (width, height) = self.screen;
+
+        let scale = match Self::scale(width) {
+            1 => minifb::Scale::X1,
+            2 | 3 => minifb::Scale::X2,
+            4..=7 => minifb::Scale::X4,
+            _ => minifb::Scale::X8,
+        };
+
         let mut window = Window::new(
             "myuchip",
-            Display::WIDTH,
-            Display::HEIGHT,
-            WindowOptions { borderless: false, title: true, resize: false, scale: minifb::Scale::X8, scale_mode: minifb::ScaleMode::Center, topmost: true, transparency: false, none: false },
+            width,
+            height,
+            WindowOptions { borderless: false, title: true, resize: false, scale, scale_mode: minifb::ScaleMode::Center, topmost: true, transparency: false, none: false },
         ).unwrap();
 
         window.set_target_fps(60);
 
-        while window.is_open() && !window.is_key_down(Key::Escape) {
-            self.keypad.borrow_mut().update_state(window.get_keys());
+        let mut memory_viewer = if self.memory_viewer {
+            MemoryViewer::new(self.core.config().load_address as usize, self.core.bus().memory().len()).map_err(|e| eprintln!("Failed to open memory viewer: {}", e)).ok()
+        } else {
+            None
+        };
 
-            self.cpu.tick();
+        let mut title = String::new();
 
-            'step_cpu: for _ in 0..Cpu::STEPS {
-                if let Some(event) = self.cpu.step() {
-                    match event {
-                        CpuEvent::Draw | CpuEvent::WaitForKey => break 'step_cpu,
-                    }
+        while window.is_open() && !window.is_key_down(Key::Escape) && !self.is_done() {
+            let keys = window.get_keys();
+
+            self.core.keypad_mut().update_state(keys.iter().filter_map(Keymap::from_key));
+            self.core.second_keypad_mut().update_state(keys.iter().filter_map(Keymap::from_second_key));
+
+            self.poll_rom();
+
+            if window.is_key_pressed(Self::SCREENSHOT, KeyRepeat::No) {
+                self.screenshot();
+            }
+
+            if window.is_key_pressed(Self::RECORD, KeyRepeat::No) {
+                self.toggle_recording();
+            }
+
+            for _ in 0..self.controls.frames_to_run(&window) {
+                if self.is_done() {
+                    break;
                 }
+
+                self.frame();
+            }
+
+            let new_title = self.controls.title(&window);
+
+            if new_title != title {
+                window.set_title(&new_title);
+                title = new_title;
             }
 
-            window.update_with_buffer(self.display.borrow().as_slice(), Display::WIDTH, Display::HEIGHT).unwrap();
+            if let Some(viewer) = &mut memory_viewer {
+                viewer.update(&mut self.core, self.controls.is_paused());
+
+                if !viewer.is_open() {
+                    memory_viewer = None;
+                }
+            }
+
+            // Re-render in case the display changed outside of a frame (reload, memory edits)
+            self.render();
+
+            window.update_with_buffer(&self.buffer, width, height).unwrap();
         }
+
+        self.finish();
     }
 }
@@ -0,0 +1,31 @@
+use myuchip_core::{config::Quirks, cpu::lint::{Quirk, QuirkLinter}};
+
+/// Prints the quirk-dependent instructions seen while running with `quirks` and the profiles the ROM is portable to
+pub fn print_report(linter: &QuirkLinter, quirks: &Quirks) {
+    let depended: Vec<Quirk> = Quirk::ALL.iter().copied().filter(|quirk| linter.depends_on(*quirk)).collect();
+
+    eprintln!("Quirk check (logic and vblank are not checked):");
+
+    if depended.is_empty() {
+        eprintln!("  No quirk-dependent behaviour seen; the ROM should run the same under every profile");
+
+        return;
+    }
+
+    for quirk in depended.iter() {
+        eprintln!("  {}: {}", quirk.name(), quirk.description());
+
+        for (_, at, count) in linter.findings().filter(|(found, _, _)| found == quirk) {
+            eprintln!("    {:03X}  {}x", at, count);
+        }
+    }
+
+    let settings: Vec<String> = depended.iter().map(|quirk| format!("{}={}", quirk.name(), quirk.get(quirks) as u8)).collect();
+
+    match linter.matching_profiles(quirks).as_slice() {
+        [] => eprintln!("  No profile matches; pin the settings in the ROM database: {}", settings.join(" ")),
+        profiles => eprintln!("  Recommended profile: {} ({})", profiles.join(" or "), settings.join(" ")),
+    }
+
+    eprintln!("  To make the ROM portable, avoid the instructions listed above");
+}
@@ -1,7 +1,39 @@
-use myuchip::{Args, Core, Parser};
+use myuchip::{Args, Command, Frontend, Parser, cfg, compare, conformance};
 
 fn main() {
-    let mut core = Core::new(Args::parse());
+    let mut args = Args::parse();
 
-    core.run();
+    match args.command.take() {
+        Some(Command::Test { manifest, filter, bless }) => {
+            match conformance::run(&manifest, filter.as_deref(), bless) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(Command::Compare { rom, monitor, interpreter, frames }) => {
+            match compare::run(&rom, &monitor, &interpreter, frames) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(Command::Cfg { rom, out, variant, load_address, entry_point }) => {
+            if let Err(e) = cfg::run(&rom, out.as_deref(), variant, load_address, entry_point) {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        None => {
+            let mut frontend = Frontend::new(args);
+
+            frontend.run();
+        }
+    }
 }
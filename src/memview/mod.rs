@@ -0,0 +1,240 @@
+use myuchip_core::{Core, bus::Address, font::Font};
+
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+
+/// Live hex dump of Chip-8 memory in a second window, one 256-byte page at a time
+///
+/// Bytes read in the last frame are highlighted green, bytes written red; PC is boxed blue and I yellow.
+/// While emulation is paused, the arrow keys move a cursor and typing hex digits edits memory through the bus, so edits
+/// reach mapped devices and respect read-only regions.
+/// `PageUp`/`PageDown` switch pages.
+pub struct MemoryViewer {
+    window: Window,
+    buffer: Vec<u32>,
+    page: usize,
+    cursor: usize,
+    memory_size: usize,
+
+    /// High nibble typed at the cursor, waiting for the low nibble
+    high_nibble: Option<u8>,
+
+    title: String,
+}
+
+impl MemoryViewer {
+    const BYTES_PER_ROW: usize = 16;
+    const ROWS: usize = 16;
+    const PAGE_SIZE: usize = Self::BYTES_PER_ROW * Self::ROWS;
+
+    const GLYPH_WIDTH: usize = 4;
+    const GLYPH_HEIGHT: usize = 5;
+    const CHAR_WIDTH: usize = Self::GLYPH_WIDTH + 1;
+
+    const MARGIN: usize = 2;
+    /// Addresses have 6 digits, for the 16 MB of MegaChip memory
+    const ADDR_DIGITS: usize = 6;
+    const ADDR_WIDTH: usize = Self::ADDR_DIGITS * Self::CHAR_WIDTH + 4;
+    const CELL_WIDTH: usize = 2 * Self::CHAR_WIDTH + 3;
+    const CELL_HEIGHT: usize = Self::GLYPH_HEIGHT + 3;
+
+    const WIDTH: usize = 2 * Self::MARGIN + Self::ADDR_WIDTH + Self::BYTES_PER_ROW * Self::CELL_WIDTH;
+    const HEIGHT: usize = 2 * Self::MARGIN + Self::ROWS * Self::CELL_HEIGHT;
+
+    const COLOR_BACKGROUND: u32 = 0x00101010;
+    const COLOR_TEXT: u32 = 0x00C0C0C0;
+    const COLOR_ADDR: u32 = 0x00707070;
+    const COLOR_READ: u32 = 0x00206020;
+    const COLOR_WRITTEN: u32 = 0x00702020;
+    const COLOR_PC: u32 = 0x004080FF;
+    const COLOR_I: u32 = 0x00FFD040;
+    const COLOR_CURSOR: u32 = 0x00FFFFFF;
+
+    /// Opens the viewer on the page holding `cursor`, for memory of `memory_size` bytes
+    pub fn new(cursor: usize, memory_size: usize) -> Result<Self, String> {
+        let window = Window::new(
+            "myuchip memory",
+            Self::WIDTH,
+            Self::HEIGHT,
+            WindowOptions { scale: Scale::X4, ..WindowOptions::default() },
+        ).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            window,
+            buffer: vec![Self::COLOR_BACKGROUND; Self::WIDTH * Self::HEIGHT],
+            page: cursor / Self::PAGE_SIZE,
+            cursor,
+            memory_size,
+            high_nibble: None,
+            title: String::new(),
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn hex_digit(key: Key) -> Option<u8> {
+        let digit = match key {
+            Key::Key0 | Key::NumPad0 => 0x0,
+            Key::Key1 | Key::NumPad1 => 0x1,
+            Key::Key2 | Key::NumPad2 => 0x2,
+            Key::Key3 | Key::NumPad3 => 0x3,
+            Key::Key4 | Key::NumPad4 => 0x4,
+            Key::Key5 | Key::NumPad5 => 0x5,
+            Key::Key6 | Key::NumPad6 => 0x6,
+            Key::Key7 | Key::NumPad7 => 0x7,
+            Key::Key8 | Key::NumPad8 => 0x8,
+            Key::Key9 | Key::NumPad9 => 0x9,
+            Key::A => 0xA,
+            Key::B => 0xB,
+            Key::C => 0xC,
+            Key::D => 0xD,
+            Key::E => 0xE,
+            Key::F => 0xF,
+            _ => return None,
+        };
+
+        Some(digit)
+    }
+
+    /// Moves the cursor, keeping its page in view
+    fn move_cursor(&mut self, delta: isize) {
+        self.cursor = (self.cursor as isize + delta).rem_euclid(self.memory_size as isize) as usize;
+        self.page = self.cursor / Self::PAGE_SIZE;
+        self.high_nibble = None;
+    }
+
+    fn handle_input(&mut self, core: &mut Core, paused: bool) {
+        for key in self.window.get_keys_pressed(KeyRepeat::Yes) {
+            match key {
+                Key::PageUp => self.move_cursor(-(Self::PAGE_SIZE as isize)),
+                Key::PageDown => self.move_cursor(Self::PAGE_SIZE as isize),
+                Key::Left if paused => self.move_cursor(-1),
+                Key::Right if paused => self.move_cursor(1),
+                Key::Up if paused => self.move_cursor(-(Self::BYTES_PER_ROW as isize)),
+                Key::Down if paused => self.move_cursor(Self::BYTES_PER_ROW as isize),
+                _ if paused => {
+                    if let Some(digit) = Self::hex_digit(key) {
+                        let addr = Address::new(self.cursor as u32);
+                        let old = core.bus().peek_byte(addr);
+
+                        match self.high_nibble.take() {
+                            None => {
+                                core.bus_mut().write_byte(addr, (digit << 4) | (old & 0xF));
+
+                                self.high_nibble = Some(digit);
+                            }
+                            Some(_) => {
+                                core.bus_mut().write_byte(addr, (old & 0xF0) | digit);
+
+                                self.move_cursor(1);
+                            }
+                        }
+
+                        // Report a rejected edit here rather than as a fault of the program's next frame
+                        if let Some(fault) = core.bus_mut().take_fault() {
+                            eprintln!("Edit rejected: {}", fault);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        for row in y..y + h {
+            self.buffer[Self::WIDTH * row + x..Self::WIDTH * row + x + w].fill(color);
+        }
+    }
+
+    /// Outlines a rectangle (1 pixel wide)
+    fn frame_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        self.fill_rect(x, y, w, 1, color);
+        self.fill_rect(x, y + h - 1, w, 1, color);
+        self.fill_rect(x, y, 1, h, color);
+        self.fill_rect(x + w - 1, y, 1, h, color);
+    }
+
+    /// Draws a hex digit using the built-in Chip-8 font
+    fn draw_digit(&mut self, x: usize, y: usize, digit: u8, color: u32) {
+        let glyph = Font::OCTO.glyph(digit);
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..Self::GLYPH_WIDTH {
+                if bits & (0x80 >> col) != 0 {
+                    self.buffer[Self::WIDTH * (y + row) + x + col] = color;
+                }
+            }
+        }
+    }
+
+    fn draw_hex(&mut self, x: usize, y: usize, value: usize, digits: usize, color: u32) {
+        for i in 0..digits {
+            let digit = (value >> (4 * (digits - 1 - i))) & 0xF;
+
+            self.draw_digit(x + i * Self::CHAR_WIDTH, y, digit as u8, color);
+        }
+    }
+
+    fn render(&mut self, core: &Core, paused: bool) {
+        self.buffer.fill(Self::COLOR_BACKGROUND);
+
+        let (pc, index) = (core.regfile().pc as usize, core.regfile().index as usize);
+        let base = self.page * Self::PAGE_SIZE;
+
+        for row in 0..Self::ROWS {
+            let y = Self::MARGIN + row * Self::CELL_HEIGHT;
+
+            self.draw_hex(Self::MARGIN, y + 1, base + row * Self::BYTES_PER_ROW, Self::ADDR_DIGITS, Self::COLOR_ADDR);
+
+            for col in 0..Self::BYTES_PER_ROW {
+                let addr = base + row * Self::BYTES_PER_ROW + col;
+                let x = Self::MARGIN + Self::ADDR_WIDTH + col * Self::CELL_WIDTH;
+
+                let log = core.bus().access_log();
+
+                if log.was_written(addr) {
+                    self.fill_rect(x, y, Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1, Self::COLOR_WRITTEN);
+                } else if log.was_read(addr) {
+                    self.fill_rect(x, y, Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1, Self::COLOR_READ);
+                }
+
+                if paused && addr == self.cursor {
+                    self.fill_rect(x, y, Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1, Self::COLOR_CURSOR);
+                }
+
+                // PC covers both bytes of the next instruction
+                if addr == pc || addr == (pc + 1) % self.memory_size {
+                    self.frame_rect(x, y, Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1, Self::COLOR_PC);
+                } else if addr == index % self.memory_size {
+                    self.frame_rect(x, y, Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1, Self::COLOR_I);
+                }
+
+                let color = if paused && addr == self.cursor { Self::COLOR_BACKGROUND } else { Self::COLOR_TEXT };
+                let value = core.bus().peek_byte(Address::new(addr as u32));
+
+                self.draw_hex(x + 2, y + 1, value as usize, 2, color);
+            }
+        }
+
+        let title = format!(
+            "myuchip memory - page {:04X}/{:04X} - PC {:06X} I {:06X}{}",
+            self.page, self.memory_size / Self::PAGE_SIZE - 1, pc, index,
+            if paused { format!(" - editing {:06X}", self.cursor) } else { String::new() },
+        );
+
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    /// Handles input and redraws; edits are only accepted while `paused`
+    pub fn update(&mut self, core: &mut Core, paused: bool) {
+        self.handle_input(core, paused);
+        self.render(core, paused);
+
+        self.window.update_with_buffer(&self.buffer, Self::WIDTH, Self::HEIGHT).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use myuchip_core::cpu::profile::Profiler;
+
+use std::{fs::File, io::{BufWriter, Write}, path::Path};
+
+/// Entries listed per table of the report
+const TOP: usize = 10;
+
+/// Name of a subroutine in the flame graph
+fn frame_name(addr: u16) -> String {
+    format!("sub_{:03X}", addr)
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    100.0 * count as f64 / u64::max(total, 1) as f64
+}
+
+/// Prints the hottest addresses, instructions, loops and subroutines
+pub fn print_report(profiler: &Profiler) {
+    let total = profiler.total();
+
+    eprintln!("Profile: {} instructions", total);
+
+    let mut addresses: Vec<_> = profiler.executions().iter().enumerate().filter(|(_, count)| **count != 0).collect();
+    addresses.sort_by(|a, b| b.1.cmp(a.1));
+
+    eprintln!("\nHottest addresses:");
+
+    for (addr, &count) in addresses.into_iter().take(TOP) {
+        eprintln!("  {:03X}  {:>12}  {:5.1}%", addr, count, percent(count, total));
+    }
+
+    eprintln!("\nInstructions:");
+
+    for (mnemonic, count) in profiler.instruction_types().into_iter().take(TOP) {
+        eprintln!("  {:<8} {:>12}  {:5.1}%", mnemonic, count, percent(count, total));
+    }
+
+    eprintln!("\nHottest loops:");
+
+    for hot_loop in profiler.loops().into_iter().take(TOP) {
+        eprintln!(
+            "  {:03X}-{:03X}  {:>12}  {:5.1}%  ({} iterations)",
+            hot_loop.start,
+            hot_loop.end,
+            hot_loop.instructions,
+            percent(hot_loop.instructions, total),
+            hot_loop.iterations,
+        );
+    }
+
+    eprintln!("\nMost expensive subroutines (inclusive, self, calls):");
+
+    for (addr, subroutine) in profiler.subroutines().into_iter().take(TOP) {
+        eprintln!(
+            "  {:03X}  {:>12}  {:5.1}%  {:>12}  {:5.1}%  {:>8}",
+            addr,
+            subroutine.inclusive,
+            percent(subroutine.inclusive, total),
+            subroutine.exclusive,
+            percent(subroutine.exclusive, total),
+            subroutine.calls,
+        );
+    }
+}
+
+/// Writes the call stacks in the collapsed format of flamegraph.pl and inferno (`main;sub_2A0;sub_31C 1234`)
+pub fn write_flame_graph(profiler: &Profiler, path: &Path) -> Result<(), String> {
+    let mut out = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+
+    for (stack, count) in profiler.stacks() {
+        let frames: Vec<_> = std::iter::once("main".to_string()).chain(stack.iter().copied().map(frame_name)).collect();
+
+        writeln!(out, "{} {}", frames.join(";"), count).map_err(|e| e.to_string())?;
+    }
+
+    out.flush().map_err(|e| e.to_string())
+}
@@ -0,0 +1,113 @@
+use crate::conformance::parse_num;
+
+use myuchip_core::{config::{ConfigError, RplFlags, Variant}, font::Font, loader::Options};
+
+use std::{collections::HashMap, convert::TryFrom, path::Path};
+
+/// Per-ROM settings, keyed by a hash of the program bytes
+///
+/// Each line of the database file is `<hash> [key=value ...]`, where the hash is the `hash` of the program as 16 hex
+/// digits. Empty lines and lines starting with `#` are ignored.
+#[derive(Default)]
+pub struct RomDb {
+    entries: HashMap<u64, Options>,
+}
+
+/// FNV-1a hash of a program
+pub fn hash(program: &[u8]) -> u64 {
+    program.iter().fold(0xCBF2_9CE4_8422_2325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value {
+        "0" | "false" => Ok(false),
+        "1" | "true" => Ok(true),
+        _ => Err(format!("invalid flag `{}`", value)),
+    }
+}
+
+pub(crate) fn parse_address(value: &str) -> Result<u16, String> {
+    u16::try_from(parse_num(value)?).map_err(|_| format!("address `{}` out of range", value))
+}
+
+pub(crate) fn parse_variant(value: &str) -> Result<Variant, String> {
+    value.parse().map_err(|e: ConfigError| e.to_string())
+}
+
+pub(crate) fn parse_font(value: &str) -> Result<Font, String> {
+    value.parse().map_err(|e: ConfigError| e.to_string())
+}
+
+pub(crate) fn parse_rpl_flags(value: &str) -> Result<RplFlags, String> {
+    value.parse().map_err(|e: ConfigError| e.to_string())
+}
+
+/// Parses a database line into its hash and options
+fn parse_entry(line: &str) -> Result<(u64, Options), String> {
+    let mut tokens = line.split_whitespace();
+
+    let hash = tokens.next().ok_or("expected `<hash>`")?;
+    let hash = u64::from_str_radix(hash, 16).map_err(|_| format!("invalid hash `{}`", hash))?;
+
+    let mut options = Options::default();
+
+    for token in tokens {
+        let (key, value) = token.split_once('=').ok_or_else(|| format!("expected `key=value`, got `{}`", token))?;
+
+        match key {
+            // Only there for the reader
+            "name" => {}
+            "variant" => options.variant = Some(parse_variant(value)?),
+            "load" => options.load_address = Some(parse_address(value)?),
+            "entry" => options.entry_point = Some(parse_address(value)?),
+            "steps" => options.steps = Some(parse_num(value)?),
+            "unlit" => options.unlit_color = Some(parse_num(value)? as u32),
+            "lit" => options.lit_color = Some(parse_num(value)? as u32),
+            "shift" => options.shift_quirk = Some(parse_flag(value)?),
+            "load-store" => options.load_store_quirk = Some(parse_flag(value)?),
+            "jump" => options.jump_quirk = Some(parse_flag(value)?),
+            "logic" => options.logic_quirk = Some(parse_flag(value)?),
+            "clip" => options.clip_quirk = Some(parse_flag(value)?),
+            "vblank" => options.vblank_quirk = Some(parse_flag(value)?),
+            "vf-order" => options.vf_order_quirk = Some(parse_flag(value)?),
+            "vip-memory" => options.vip_memory_map = Some(parse_flag(value)?),
+            "font" => options.font = Some(parse_font(value)?),
+            "font-address" => options.font_address = Some(parse_address(value)?),
+            "rpl-flags" => options.rpl_flags = Some(parse_rpl_flags(value)?),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+    }
+
+    Ok((hash, options))
+}
+
+impl RomDb {
+    /// Reads a database file; a missing file gives an empty database
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let mut entries = HashMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (hash, options) = parse_entry(line).map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e))?;
+
+            entries.insert(hash, options);
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn lookup(&self, program: &[u8]) -> Option<&Options> {
+        self.entries.get(&hash(program))
+    }
+}
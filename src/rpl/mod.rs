@@ -0,0 +1,66 @@
+use crate::romdb;
+
+use std::{env, path::PathBuf};
+
+/// Per-user data directory: `$XDG_DATA_HOME/myuchip`, `~/.local/share/myuchip`, `%APPDATA%\myuchip` on Windows or
+/// `~/Library/Application Support/myuchip` on macOS
+fn data_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from);
+
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from).or_else(|| home().map(|home| home.join(".local/share")))
+    };
+
+    base.map(|base| base.join("myuchip"))
+}
+
+/// RPL user flags of one ROM, kept in `<data dir>/rpl/<hash>.bin` between sessions as the HP 48 keeps them in memory
+///
+/// The hash is the one the ROM database uses, so every copy of a program shares its flags (and its high scores).
+pub struct FlagStore {
+    path: PathBuf,
+
+    /// Flags as last read or written
+    saved: Vec<u8>,
+}
+
+impl FlagStore {
+    /// Finds the flag file of `program` and restores the flags saved in it, if any, into `flags`
+    pub fn open(program: &[u8], flags: &mut [u8]) -> Result<Self, String> {
+        let dir = data_dir().ok_or("no user data directory (HOME is not set)")?.join("rpl");
+        let path = dir.join(format!("{:016X}.bin", romdb::hash(program)));
+
+        match std::fs::read(&path) {
+            Ok(data) => {
+                let len = usize::min(data.len(), flags.len());
+
+                flags[..len].copy_from_slice(&data[..len]);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        }
+
+        Ok(Self { path, saved: flags.to_vec() })
+    }
+
+    /// Writes the flags if they changed since they were last saved
+    pub fn save(&mut self, flags: &[u8]) -> Result<(), String> {
+        if flags == self.saved.as_slice() {
+            return Ok(());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+
+        std::fs::write(&self.path, flags).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+
+        self.saved = flags.to_vec();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Polls a ROM file for changes
+pub struct RomWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    rom: Vec<u8>,
+    last_poll: Instant,
+}
+
+impl RomWatcher {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Starts watching `path`, whose current contents are `rom`
+    pub fn new(path: PathBuf, rom: Vec<u8>) -> Self {
+        let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+
+        Self { path, modified, rom, last_poll: Instant::now() }
+    }
+
+    /// Returns the new contents if the file changed since the last successful poll
+    ///
+    /// Empty or unreadable files are skipped, since the ROM is usually caught halfway through being rebuilt.
+    pub fn poll(&mut self) -> Option<&[u8]> {
+        if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+            return None;
+        }
+
+        self.last_poll = Instant::now();
+
+        let modified = std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+
+        if modified == self.modified {
+            return None;
+        }
+
+        let rom = std::fs::read(&self.path).ok().filter(|rom| !rom.is_empty())?;
+
+        self.modified = modified;
+
+        if rom == self.rom {
+            return None;
+        }
+
+        self.rom = rom;
+
+        Some(&self.rom)
+    }
+}